@@ -28,9 +28,25 @@ fn main() {
     println!("cargo:rustc-link-lib=OSDependent.glsltospirv");
     println!("cargo:rustc-link-lib=SPIRV.glsltospirv");
     println!("cargo:rustc-link-lib=SPVRemapper.glsltospirv");
+    // Only these targets ship SPIRV-Tools, which backs validation, and a
+    // glslang build whose C++ symbols were checked, see src/tshader.rs.
+    println!("cargo:rustc-check-cfg=cfg(spirv_tools)");
+    println!("cargo:rustc-check-cfg=cfg(glslang_cpp)");
     if target.contains("x86_64-unknown-linux-gnu") || target.contains("x86_64-apple-darwin") {
+        // The SPIR-V generator of these glslang builds references the
+        // SPIRV-Tools optimizer for HLSL legalization, so it has to be linked
+        // even though the optimizer is never run.
+        if !bin_dir.join("libSPIRV-Tools-opt.glsltospirv.a").exists() {
+            println!(
+                "cargo:warning=libSPIRV-Tools-opt.glsltospirv.a is missing from {}, \
+                 linking will fail",
+                bin_dir.display()
+            );
+        }
         println!("cargo:rustc-link-lib=SPIRV-Tools-opt.glsltospirv");
         println!("cargo:rustc-link-lib=SPIRV-Tools.glsltospirv");
+        println!("cargo:rustc-cfg=spirv_tools");
+        println!("cargo:rustc-cfg=glslang_cpp");
     }
     if target.contains("android") {
         println!("cargo:rustc-link-lib=c++_shared");
@@ -0,0 +1,707 @@
+//! Caching of compiled shaders, keyed by everything that affects the output.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+use crate::{
+    preamble, prepare_source, tshader, Assignment, Block, BlockMember, CompileError,
+    CompileOptions, CompileOutput, Compiler, Diagnostic, EntryPoint, InterfaceVariable,
+    PushConstantRange, Reflection, Resource, ResourceKind, Severity, ShaderType,
+    SpecializationConstant,
+};
+
+/// Compiles shaders through a cache, skipping glslang for inputs it has seen.
+///
+/// Entries are keyed by the source, the files pulled in through `#include`,
+/// the shader type, the options and the glslang version. The most recently
+/// used outputs are kept in memory, and with a [`directory`](Self::directory)
+/// every output is also written to disk so it survives restarts. Files are
+/// named after a hash of the key and store the key in full, so an output is
+/// only loaded for the input it was compiled from. Failed compiles aren't
+/// cached.
+///
+/// Disk errors are ignored, leaving the shader to be compiled again.
+pub struct ShaderCache {
+    compiler: Compiler,
+    memory: Mutex<Lru>,
+    disk: Option<DiskCache>,
+}
+
+/// How often a [`ShaderCache`] found an output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CacheStats {
+    pub memory_hits: u64,
+    pub disk_hits: u64,
+    pub misses: u64,
+}
+
+impl ShaderCache {
+    /// A cache keeping up to `capacity` outputs in memory.
+    pub fn new(capacity: usize) -> Self {
+        ShaderCache {
+            compiler: Compiler::new(),
+            memory: Mutex::new(Lru {
+                capacity,
+                tick: 0,
+                entries: HashMap::new(),
+                stats: CacheStats::default(),
+            }),
+            disk: None,
+        }
+    }
+
+    /// Also stores outputs in `path`, which is created when needed. Once the
+    /// files take more than `max_size` bytes, the least recently used ones
+    /// are deleted.
+    pub fn directory<P: Into<PathBuf>>(mut self, path: P, max_size: u64) -> Self {
+        self.disk = Some(DiskCache {
+            path: path.into(),
+            max_size,
+        });
+        self
+    }
+
+    pub fn compile(
+        &self,
+        code: &str,
+        ty: ShaderType,
+        shader_defs: Option<&[String]>,
+    ) -> Result<CompileOutput, CompileError> {
+        let mut options = CompileOptions::new();
+        if let Some(defs) = shader_defs {
+            options = options.shader_defs(defs);
+        }
+        self.compile_with_options(code, ty, &options)
+    }
+
+    pub fn compile_with_options(
+        &self,
+        code: &str,
+        ty: ShaderType,
+        options: &CompileOptions,
+    ) -> Result<CompileOutput, CompileError> {
+        let key = key(code, ty, options)?;
+
+        {
+            let mut memory = self.memory();
+            if let Some(output) = memory.get(&key) {
+                memory.stats.memory_hits += 1;
+                return Ok(output);
+            }
+        }
+        if let Some(output) = self.disk.as_ref().and_then(|disk| disk.load(&key)) {
+            let mut memory = self.memory();
+            memory.stats.disk_hits += 1;
+            memory.insert(key, output.clone());
+            return Ok(output);
+        }
+
+        self.memory().stats.misses += 1;
+        let output = self.compiler.compile(code, ty, options)?;
+        if let Some(disk) = &self.disk {
+            disk.store(&key, &output);
+        }
+        self.memory().insert(key, output.clone());
+        Ok(output)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.memory().stats
+    }
+
+    /// Empties the memory cache. Files on disk are kept.
+    pub fn clear(&self) {
+        self.memory().entries.clear();
+    }
+
+    fn memory(&self) -> std::sync::MutexGuard<'_, Lru> {
+        self.memory.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for ShaderCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let memory = self.memory();
+        f.debug_struct("ShaderCache")
+            .field("capacity", &memory.capacity)
+            .field("len", &memory.entries.len())
+            .field("directory", &self.disk.as_ref().map(|disk| &disk.path))
+            .field("stats", &memory.stats)
+            .finish()
+    }
+}
+
+fn key(code: &str, ty: ShaderType, options: &CompileOptions) -> Result<Key, CompileError> {
+    let mut includes = Vec::new();
+    let source = prepare_source(code, options, &preamble(options)?, &mut includes)?;
+
+    let mut key = Key::default();
+    env!("CARGO_PKG_VERSION").hash(&mut key);
+    tshader::spirv_generator_version().hash(&mut key);
+    source.hash(&mut key);
+    includes.hash(&mut key);
+    ty.hash(&mut key);
+    options.hash_output_options(&mut key);
+    Ok(key)
+}
+
+/// Every byte fed to it as a [`Hasher`], which identifies an input exactly.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct Key(Vec<u8>);
+
+impl Hasher for Key {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    /// 64 bit FNV-1a of the bytes, which unlike the standard library's hasher
+    /// is the same in every run.
+    fn finish(&self) -> u64 {
+        self.0.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+}
+
+struct Lru {
+    capacity: usize,
+    tick: u64,
+    /// Outputs with the tick of their last use.
+    entries: HashMap<Key, (u64, CompileOutput)>,
+    stats: CacheStats,
+}
+
+impl Lru {
+    fn get(&mut self, key: &Key) -> Option<CompileOutput> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(key).map(|(used, output)| {
+            *used = tick;
+            output.clone()
+        })
+    }
+
+    fn insert(&mut self, key: Key, output: CompileOutput) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self.entries.iter().min_by_key(|(_, (used, _))| *used);
+            if let Some(oldest) = oldest.map(|(key, _)| key.clone()) {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(key, (self.tick, output));
+    }
+}
+
+struct DiskCache {
+    path: PathBuf,
+    max_size: u64,
+}
+
+/// Start of every cache file, followed by the format version and the key.
+const MAGIC: &[u8; 4] = b"GSPC";
+const FORMAT_VERSION: u32 = 4;
+const EXTENSION: &str = "spvcache";
+
+impl DiskCache {
+    fn file(&self, key: &Key) -> PathBuf {
+        self.path
+            .join(format!("{:016x}.{}", key.finish(), EXTENSION))
+    }
+
+    fn load(&self, key: &Key) -> Option<CompileOutput> {
+        let path = self.file(key);
+        let bytes = fs::read(&path).ok()?;
+        match decode(&bytes) {
+            Some((stored, output)) if stored == key.0 => {
+                // Marks the file as recently used for eviction.
+                if let Ok(file) = fs::File::options().write(true).open(&path) {
+                    let _ = file.set_modified(SystemTime::now());
+                }
+                Some(output)
+            }
+            // Another input whose key has the same hash, which the output of
+            // this one replaces.
+            Some(_) => None,
+            None => {
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    fn store(&self, key: &Key, output: &CompileOutput) {
+        static TEMPORARY: AtomicUsize = AtomicUsize::new(0);
+
+        if fs::create_dir_all(&self.path).is_err() {
+            return;
+        }
+        // Written next to the entry and renamed, so that other processes
+        // never read a partial file.
+        let temporary = self.path.join(format!(
+            "{:016x}.{}.{}.tmp",
+            key.finish(),
+            process::id(),
+            TEMPORARY.fetch_add(1, Ordering::Relaxed)
+        ));
+        if fs::write(&temporary, encode(key, output)).is_err()
+            || fs::rename(&temporary, self.file(key)).is_err()
+        {
+            let _ = fs::remove_file(&temporary);
+            return;
+        }
+        self.evict();
+    }
+
+    /// Deletes the least recently used files until they fit in `max_size`.
+    fn evict(&self) {
+        let entries = match fs::read_dir(&self.path) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        let mut files: Vec<_> = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                if !is_cache_file(&entry.path()) {
+                    return None;
+                }
+                let metadata = entry.metadata().ok()?;
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                Some((modified, metadata.len(), entry.path()))
+            })
+            .collect();
+
+        let mut size: u64 = files.iter().map(|(_, len, _)| len).sum();
+        files.sort();
+        for (_, len, path) in files {
+            if size <= self.max_size {
+                break;
+            }
+            if fs::remove_file(path).is_ok() {
+                size -= len;
+            }
+        }
+    }
+}
+
+fn is_cache_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == EXTENSION)
+}
+
+const STAGES: [ShaderType; 14] = [
+    ShaderType::Vertex,
+    ShaderType::Fragment,
+    ShaderType::Geometry,
+    ShaderType::TessellationControl,
+    ShaderType::TessellationEvaluation,
+    ShaderType::Compute,
+    ShaderType::Task,
+    ShaderType::Mesh,
+    ShaderType::RayGeneration,
+    ShaderType::Intersection,
+    ShaderType::AnyHit,
+    ShaderType::ClosestHit,
+    ShaderType::Miss,
+    ShaderType::Callable,
+];
+
+const SEVERITIES: [Severity; 3] = [Severity::Error, Severity::Warning, Severity::Note];
+
+const RESOURCE_KINDS: [ResourceKind; 7] = [
+    ResourceKind::Sampler,
+    ResourceKind::SampledImage,
+    ResourceKind::CombinedImageSampler,
+    ResourceKind::StorageImage,
+    ResourceKind::UniformTexelBuffer,
+    ResourceKind::StorageTexelBuffer,
+    ResourceKind::AccelerationStructure,
+];
+
+/// Serializes an output along with its key. The reflection is stored rather
+/// than rebuilt from the SPIR-V, which may have had its names stripped.
+fn encode(key: &Key, output: &CompileOutput) -> Vec<u8> {
+    let mut writer = Writer(MAGIC.to_vec());
+    writer.u32(FORMAT_VERSION);
+    writer.bytes(&key.0);
+
+    writer.len(output.spirv.len());
+    for &word in &output.spirv {
+        writer.u32(word);
+    }
+    writer.option(output.entry_point.as_deref(), Writer::str);
+
+    writer.len(output.assignments.len());
+    for assignment in &output.assignments {
+        match assignment {
+            Assignment::Binding {
+                stage,
+                name,
+                set,
+                binding,
+            } => {
+                writer.u8(0);
+                writer.stage(*stage);
+                writer.str(name);
+                writer.u32(*set);
+                writer.u32(*binding);
+            }
+            Assignment::Location {
+                stage,
+                name,
+                location,
+            } => {
+                writer.u8(1);
+                writer.stage(*stage);
+                writer.str(name);
+                writer.u32(*location);
+            }
+        }
+    }
+
+    writer.len(output.diagnostics.len());
+    for diagnostic in &output.diagnostics {
+        let severity = SEVERITIES.iter().position(|&s| s == diagnostic.severity);
+        writer.u8(severity.unwrap() as u8);
+        writer.option(diagnostic.source.as_ref(), |writer, &source| {
+            writer.u32(source)
+        });
+        writer.option(diagnostic.line.as_ref(), |writer, &line| writer.u32(line));
+        writer.option(diagnostic.token.as_deref(), Writer::str);
+        writer.str(&diagnostic.message);
+    }
+
+    writer.len(output.includes.len());
+    for include in &output.includes {
+        writer.str(include);
+    }
+    encode_reflection(&mut writer, &output.reflection);
+    writer.0
+}
+
+fn encode_reflection(writer: &mut Writer, reflection: &Reflection) {
+    fn members(writer: &mut Writer, members: &[BlockMember]) {
+        writer.len(members.len());
+        for member in members {
+            writer.str(&member.name);
+            writer.u32(member.offset);
+            writer.u32(member.size);
+            writer.str(&member.ty);
+        }
+    }
+
+    fn blocks(writer: &mut Writer, blocks: &[Block]) {
+        writer.len(blocks.len());
+        for block in blocks {
+            writer.str(&block.name);
+            writer.u32(block.set);
+            writer.u32(block.binding);
+            writer.u32(block.count);
+            writer.u32(block.size);
+            members(writer, &block.members);
+        }
+    }
+
+    fn variables(writer: &mut Writer, variables: &[InterfaceVariable]) {
+        writer.len(variables.len());
+        for variable in variables {
+            writer.str(&variable.name);
+            writer.u32(variable.location);
+            writer.u32(variable.component);
+            writer.str(&variable.ty);
+        }
+    }
+
+    blocks(writer, &reflection.uniform_blocks);
+    blocks(writer, &reflection.storage_buffers);
+
+    writer.len(reflection.resources.len());
+    for resource in &reflection.resources {
+        writer.str(&resource.name);
+        writer.u32(resource.set);
+        writer.u32(resource.binding);
+        writer.u32(resource.count);
+        let kind = RESOURCE_KINDS.iter().position(|&k| k == resource.kind);
+        writer.u8(kind.unwrap() as u8);
+    }
+
+    variables(writer, &reflection.inputs);
+    variables(writer, &reflection.outputs);
+
+    writer.option(reflection.push_constants.as_ref(), |writer, range| {
+        writer.str(&range.name);
+        writer.u32(range.offset);
+        writer.u32(range.size);
+        members(writer, &range.members);
+    });
+
+    writer.len(reflection.specialization_constants.len());
+    for constant in &reflection.specialization_constants {
+        writer.u32(constant.id);
+        writer.str(&constant.name);
+        writer.str(&constant.ty);
+        writer.u64(constant.default);
+    }
+
+    writer.len(reflection.entry_points.len());
+    for entry_point in &reflection.entry_points {
+        writer.str(&entry_point.name);
+        writer.option(entry_point.stage, Writer::stage);
+        writer.option(entry_point.workgroup_size, |writer, size| {
+            for dimension in size {
+                writer.u32(dimension);
+            }
+        });
+    }
+}
+
+/// The key and output of an entry.
+fn decode(bytes: &[u8]) -> Option<(&[u8], CompileOutput)> {
+    let mut reader = Reader(bytes);
+    if reader.take(MAGIC.len())? != MAGIC || reader.u32()? != FORMAT_VERSION {
+        return None;
+    }
+    let key = reader.bytes()?;
+
+    let spirv = reader.list(Reader::u32)?;
+    let entry_point = reader.option(Reader::string)?;
+
+    let assignments = reader.list(|reader| match reader.u8()? {
+        0 => Some(Assignment::Binding {
+            stage: reader.stage()?,
+            name: reader.string()?,
+            set: reader.u32()?,
+            binding: reader.u32()?,
+        }),
+        1 => Some(Assignment::Location {
+            stage: reader.stage()?,
+            name: reader.string()?,
+            location: reader.u32()?,
+        }),
+        _ => None,
+    })?;
+
+    let diagnostics = reader.list(|reader| {
+        Some(Diagnostic {
+            severity: *SEVERITIES.get(reader.u8()? as usize)?,
+            source: reader.option(Reader::u32)?,
+            line: reader.option(Reader::u32)?,
+            token: reader.option(Reader::string)?,
+            message: reader.string()?,
+        })
+    })?;
+
+    let includes = reader.list(Reader::string)?;
+    let reflection = decode_reflection(&mut reader)?;
+    if !reader.0.is_empty() {
+        return None;
+    }
+
+    let output = CompileOutput {
+        reflection,
+        spirv,
+        entry_point,
+        assignments,
+        diagnostics,
+        includes,
+    };
+    Some((key, output))
+}
+
+fn decode_reflection(reader: &mut Reader<'_>) -> Option<Reflection> {
+    fn members(reader: &mut Reader<'_>) -> Option<Vec<BlockMember>> {
+        reader.list(|reader| {
+            Some(BlockMember {
+                name: reader.string()?,
+                offset: reader.u32()?,
+                size: reader.u32()?,
+                ty: reader.string()?,
+            })
+        })
+    }
+
+    fn blocks(reader: &mut Reader<'_>) -> Option<Vec<Block>> {
+        reader.list(|reader| {
+            Some(Block {
+                name: reader.string()?,
+                set: reader.u32()?,
+                binding: reader.u32()?,
+                count: reader.u32()?,
+                size: reader.u32()?,
+                members: members(reader)?,
+            })
+        })
+    }
+
+    fn variables(reader: &mut Reader<'_>) -> Option<Vec<InterfaceVariable>> {
+        reader.list(|reader| {
+            Some(InterfaceVariable {
+                name: reader.string()?,
+                location: reader.u32()?,
+                component: reader.u32()?,
+                ty: reader.string()?,
+            })
+        })
+    }
+
+    Some(Reflection {
+        uniform_blocks: blocks(reader)?,
+        storage_buffers: blocks(reader)?,
+        resources: reader.list(|reader| {
+            Some(Resource {
+                name: reader.string()?,
+                set: reader.u32()?,
+                binding: reader.u32()?,
+                count: reader.u32()?,
+                kind: *RESOURCE_KINDS.get(reader.u8()? as usize)?,
+            })
+        })?,
+        inputs: variables(reader)?,
+        outputs: variables(reader)?,
+        push_constants: reader.option(|reader| {
+            Some(PushConstantRange {
+                name: reader.string()?,
+                offset: reader.u32()?,
+                size: reader.u32()?,
+                members: members(reader)?,
+            })
+        })?,
+        specialization_constants: reader.list(|reader| {
+            Some(SpecializationConstant {
+                id: reader.u32()?,
+                name: reader.string()?,
+                ty: reader.string()?,
+                default: reader.u64()?,
+            })
+        })?,
+        entry_points: reader.list(|reader| {
+            Some(EntryPoint {
+                name: reader.string()?,
+                stage: reader.option(Reader::stage)?,
+                workgroup_size: reader
+                    .option(|reader| Some([reader.u32()?, reader.u32()?, reader.u32()?]))?,
+            })
+        })?,
+    })
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) {
+        self.u64(len as u64);
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.len(value.len());
+        self.0.extend_from_slice(value);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    fn stage(&mut self, stage: ShaderType) {
+        let index = STAGES.iter().position(|&s| s == stage);
+        self.u8(index.unwrap() as u8);
+    }
+
+    fn option<T>(&mut self, value: Option<T>, write: impl FnOnce(&mut Self, T)) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                write(self, value);
+            }
+            None => self.u8(0),
+        }
+    }
+}
+
+/// Reads what [`Writer`] wrote, returning `None` for anything malformed.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.0.len() {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Some(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Some(u64::from_le_bytes(bytes))
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        usize::try_from(self.u64()?).ok()
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.len()?;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        String::from_utf8(self.bytes()?.to_vec()).ok()
+    }
+
+    fn stage(&mut self) -> Option<ShaderType> {
+        STAGES.get(self.u8()? as usize).copied()
+    }
+
+    /// The outer `Option` is `None` if the value is malformed.
+    fn option<T>(&mut self, read: impl FnOnce(&mut Self) -> Option<T>) -> Option<Option<T>> {
+        match self.u8()? {
+            0 => Some(None),
+            1 => read(self).map(Some),
+            _ => None,
+        }
+    }
+
+    fn list<T>(&mut self, mut read: impl FnMut(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+        let len = self.len()?;
+        // Every element takes at least a byte, which bounds the allocation
+        // for corrupted lengths.
+        if len > self.0.len() {
+            return None;
+        }
+        (0..len).map(|_| read(self)).collect()
+    }
+}
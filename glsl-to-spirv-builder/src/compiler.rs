@@ -0,0 +1,138 @@
+//! A shareable handle on glslang's process-wide state.
+
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+
+use crate::glslang::Process;
+use crate::{CompileError, CompileOptions, CompileOutput, ProgramOutput, ShaderType};
+
+/// One shader of a [`compile_batch`](crate::compile_batch).
+#[derive(Debug, Clone, Copy)]
+pub struct CompileJob<'a> {
+    pub code: &'a str,
+    pub ty: ShaderType,
+    /// Added to the definitions of the batch options, as with
+    /// [`CompileOptions::shader_defs`].
+    pub shader_defs: &'a [String],
+}
+
+impl<'a> CompileJob<'a> {
+    pub fn new(code: &'a str, ty: ShaderType) -> Self {
+        CompileJob {
+            code,
+            ty,
+            shader_defs: &[],
+        }
+    }
+
+    pub fn shader_defs(mut self, shader_defs: &'a [String]) -> Self {
+        self.shader_defs = shader_defs;
+        self
+    }
+}
+
+/// Handle keeping glslang initialized between compiles.
+///
+/// glslang's process-wide state is set up when the first handle is created
+/// and torn down when the last one is dropped. Free functions such as
+/// [`compile`](crate::compile) share a handle that lives until the process
+/// exits, so separate handles are only needed to release that state.
+///
+/// `Compiler` is `Send` and `Sync`. Any number of threads may compile through
+/// the same handle at once, as every compile uses glslang objects of its own.
+#[derive(Clone)]
+pub struct Compiler {
+    _process: Arc<Process>,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Compiler {
+            _process: Process::acquire(),
+        }
+    }
+
+    /// The handle used by the free functions.
+    pub(crate) fn shared() -> &'static Compiler {
+        static SHARED: OnceLock<Compiler> = OnceLock::new();
+        SHARED.get_or_init(Compiler::new)
+    }
+
+    /// See [`compile_with_options`](crate::compile_with_options).
+    pub fn compile(
+        &self,
+        code: &str,
+        ty: ShaderType,
+        options: &CompileOptions,
+    ) -> Result<CompileOutput, CompileError> {
+        crate::compile_shader(code, ty, options)
+    }
+
+    /// See [`compile_program`](crate::compile_program).
+    pub fn compile_program(
+        &self,
+        shaders: &[(&str, ShaderType)],
+        options: &CompileOptions,
+    ) -> Result<ProgramOutput, CompileError> {
+        crate::compile_inner(shaders.iter().copied(), options)
+    }
+
+    /// See [`compile_batch_with_options`](crate::compile_batch_with_options).
+    pub fn compile_batch(
+        &self,
+        jobs: &[CompileJob<'_>],
+        options: &CompileOptions,
+    ) -> Vec<Result<CompileOutput, CompileError>> {
+        let threads = thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(jobs.len());
+        let next = AtomicUsize::new(0);
+        let compile_next = || {
+            let mut results = Vec::new();
+            loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let job = match jobs.get(index) {
+                    Some(job) => job,
+                    None => return results,
+                };
+                let options = options.clone().shader_defs(job.shader_defs);
+                results.push((index, self.compile(job.code, job.ty, &options)));
+            }
+        };
+
+        let mut results: Vec<_> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads).map(|_| scope.spawn(compile_next)).collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        results.sort_unstable_by_key(|&(index, _)| index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// See [`preprocess_with_options`](crate::preprocess_with_options).
+    pub fn preprocess(
+        &self,
+        code: &str,
+        ty: ShaderType,
+        options: &CompileOptions,
+    ) -> Result<String, CompileError> {
+        crate::preprocess_shader(code, ty, options)
+    }
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Compiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Compiler")
+    }
+}
@@ -0,0 +1,410 @@
+//! Tracking of preprocessor conditionals, so includes in inactive branches
+//! can be skipped before glslang runs its own preprocessor.
+//!
+//! Macros glslang predefines, such as extension names, aren't known here.
+//! Conditions depending on them are [`Branch::Unknown`] and left for glslang.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::source;
+use crate::{CompileOptions, SourceLanguage, TargetEnv};
+
+// Guards against macros expanding to themselves.
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+/// Whether the code at some point of a source is compiled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Branch {
+    Active,
+    Inactive,
+    Unknown,
+}
+
+impl Branch {
+    fn from_value(value: Option<i64>) -> Self {
+        match value {
+            Some(0) => Branch::Inactive,
+            Some(_) => Branch::Active,
+            None => Branch::Unknown,
+        }
+    }
+
+    /// The state of a branch taken on `condition` inside `self`.
+    fn within(self, condition: Branch) -> Self {
+        match (self, condition) {
+            (Branch::Inactive, _) | (_, Branch::Inactive) => Branch::Inactive,
+            (Branch::Active, condition) => condition,
+            (Branch::Unknown, _) => Branch::Unknown,
+        }
+    }
+}
+
+enum Macro {
+    Object(String),
+    Function,
+    /// Defined or undefined in a branch of unknown state.
+    Unknown,
+}
+
+/// A `#if` block. `taken` is whether any of its branches so far was taken.
+struct Frame {
+    parent: Branch,
+    current: Branch,
+    taken: Branch,
+}
+
+/// The macros and open conditional blocks of the code seen so far.
+pub(crate) struct Conditionals {
+    macros: HashMap<String, Macro>,
+    frames: Vec<Frame>,
+}
+
+impl Conditionals {
+    pub fn new(options: &CompileOptions) -> Self {
+        let mut macros = HashMap::new();
+        for (name, value) in &options.defines {
+            let value = value.clone().unwrap_or_default();
+            macros.insert(name.clone(), Macro::Object(value));
+        }
+        match (options.source_language, options.target_env) {
+            (SourceLanguage::Glsl, TargetEnv::OpenGl4_5) => {}
+            (SourceLanguage::Glsl, _) => {
+                macros.insert("VULKAN".to_string(), Macro::Object("100".to_string()));
+            }
+            (SourceLanguage::Hlsl, _) => {
+                macros.insert("VULKAN".to_string(), Macro::Unknown);
+            }
+        }
+        Conditionals {
+            macros,
+            frames: Vec::new(),
+        }
+    }
+
+    /// The state of the code at the current line.
+    pub fn state(&self) -> Branch {
+        self.frames
+            .last()
+            .map_or(Branch::Active, |frame| frame.current)
+    }
+
+    pub fn defined(&self, name: &str) -> Branch {
+        match self.macros.get(name) {
+            Some(Macro::Unknown) => Branch::Unknown,
+            Some(_) => Branch::Active,
+            // Reserved names may be predefined by glslang.
+            None if name.starts_with("GL_") || name.contains("__") => Branch::Unknown,
+            None => Branch::Inactive,
+        }
+    }
+
+    /// Follows a line of code, which only matters if it is a conditional,
+    /// `#define` or `#undef` directive.
+    pub fn line(&mut self, code_line: &str) {
+        let state = self.state();
+        if let Some(args) = source::directive(code_line, "if") {
+            let condition = Branch::from_value(self.evaluate(args));
+            self.enter(condition);
+        } else if let Some(args) = source::directive(code_line, "ifdef") {
+            let condition = self.defined(identifier(args));
+            self.enter(condition);
+        } else if let Some(args) = source::directive(code_line, "ifndef") {
+            let condition = match self.defined(identifier(args)) {
+                Branch::Active => Branch::Inactive,
+                Branch::Inactive => Branch::Active,
+                Branch::Unknown => Branch::Unknown,
+            };
+            self.enter(condition);
+        } else if let Some(args) = source::directive(code_line, "elif") {
+            let condition = Branch::from_value(self.evaluate(args));
+            self.next_branch(condition);
+        } else if source::directive(code_line, "else").is_some() {
+            self.next_branch(Branch::Active);
+        } else if source::directive(code_line, "endif").is_some() {
+            self.frames.pop();
+        } else if let Some(args) = source::directive(code_line, "define") {
+            let name = identifier(args);
+            let definition = match args[name.len()..].strip_prefix('(') {
+                Some(_) => Macro::Function,
+                None => Macro::Object(args[name.len()..].trim().to_string()),
+            };
+            match state {
+                Branch::Active => self.macros.insert(name.to_string(), definition),
+                Branch::Unknown => self.macros.insert(name.to_string(), Macro::Unknown),
+                Branch::Inactive => None,
+            };
+        } else if let Some(args) = source::directive(code_line, "undef") {
+            let name = identifier(args);
+            match state {
+                Branch::Active => self.macros.remove(name),
+                Branch::Unknown => self.macros.insert(name.to_string(), Macro::Unknown),
+                Branch::Inactive => None,
+            };
+        }
+    }
+
+    fn enter(&mut self, condition: Branch) {
+        let parent = self.state();
+        self.frames.push(Frame {
+            parent,
+            current: parent.within(condition),
+            // No later branch of an inactive block is taken either.
+            taken: if parent == Branch::Inactive {
+                Branch::Active
+            } else {
+                condition
+            },
+        });
+    }
+
+    fn next_branch(&mut self, condition: Branch) {
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+        frame.current = match frame.taken {
+            Branch::Active => Branch::Inactive,
+            Branch::Inactive => frame.parent.within(condition),
+            Branch::Unknown => frame.parent.within(condition).within(Branch::Unknown),
+        };
+        frame.taken = match (frame.taken, condition) {
+            (Branch::Active, _) | (_, Branch::Active) => Branch::Active,
+            (Branch::Inactive, Branch::Inactive) => Branch::Inactive,
+            _ => Branch::Unknown,
+        };
+    }
+
+    /// Evaluates the expression of a `#if`, or returns `None` if it depends on
+    /// something unknown or can't be parsed.
+    fn evaluate(&self, expression: &str) -> Option<i64> {
+        let tokens = self.expand(expression, 0);
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let value = parser.binary(0).ok()?;
+        if parser.position != tokens.len() {
+            return None;
+        }
+        value
+    }
+
+    /// Tokenizes `text`, replacing macros by their values and `defined`
+    /// operators by their result. Unknown values become `#`.
+    fn expand(&self, text: &str, depth: usize) -> Vec<String> {
+        let mut expanded = Vec::new();
+        let mut tokens = tokenize(text).into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            if token == "defined" {
+                let parenthesized = tokens.peek() == Some(&"(");
+                if parenthesized {
+                    tokens.next();
+                }
+                let name = tokens.next().unwrap_or("");
+                if parenthesized && tokens.next() != Some(")") {
+                    expanded.push("#".to_string());
+                }
+                expanded.push(
+                    match self.defined(name) {
+                        Branch::Active => "1",
+                        Branch::Inactive => "0",
+                        Branch::Unknown => "#",
+                    }
+                    .to_string(),
+                );
+            } else if token.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                match self.macros.get(token) {
+                    Some(Macro::Object(value)) if depth < MAX_EXPANSION_DEPTH => {
+                        expanded.extend(self.expand(value, depth + 1));
+                    }
+                    Some(_) => expanded.push("#".to_string()),
+                    None if self.defined(token) == Branch::Unknown => {
+                        expanded.push("#".to_string())
+                    }
+                    // Undefined identifiers are 0 in conditions.
+                    None => expanded.push("0".to_string()),
+                }
+            } else {
+                expanded.push(token.to_string());
+            }
+        }
+        expanded
+    }
+}
+
+/// The macro name at the start of the arguments of a directive.
+fn identifier(args: &str) -> &str {
+    let end = args
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(args.len());
+    &args[..end]
+}
+
+const OPERATORS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "|", "^", "&", "<", ">", "+", "-", "*", "/",
+    "%", "!", "~", "(", ")", "#",
+];
+
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while let Some(first) = rest.chars().next() {
+        let len = if first.is_ascii_alphanumeric() || first == '_' {
+            rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len())
+        } else {
+            OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(*op))
+                .map_or(first.len_utf8(), |op| op.len())
+        };
+        tokens.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    tokens
+}
+
+/// Precedence climbing over expanded tokens. `Err` is a syntax error, and
+/// `Ok(None)` a value depending on something unknown.
+struct Parser<'a> {
+    tokens: &'a [String],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+        Some(token)
+    }
+
+    fn binary(&mut self, min_precedence: u8) -> Result<Option<i64>, ()> {
+        let mut left = self.unary()?;
+        while let Some(op) = self.tokens.get(self.position) {
+            let precedence = match precedence(op) {
+                Some(precedence) if precedence >= min_precedence => precedence,
+                _ => break,
+            };
+            let op = op.clone();
+            self.position += 1;
+            let right = self.binary(precedence + 1)?;
+            left = apply(&op, left, right);
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Option<i64>, ()> {
+        match self.next().ok_or(())? {
+            "(" => {
+                let value = self.binary(0)?;
+                match self.next() {
+                    Some(")") => Ok(value),
+                    _ => Err(()),
+                }
+            }
+            "!" => Ok(self.unary()?.map(|value| (value == 0) as i64)),
+            "-" => Ok(self.unary()?.map(i64::wrapping_neg)),
+            "+" => self.unary(),
+            "~" => Ok(self.unary()?.map(|value| !value)),
+            "#" => Ok(None),
+            number => parse_number(number).map(Some).ok_or(()),
+        }
+    }
+}
+
+fn precedence(op: &str) -> Option<u8> {
+    Some(match op {
+        "||" => 1,
+        "&&" => 2,
+        "|" => 3,
+        "^" => 4,
+        "&" => 5,
+        "==" | "!=" => 6,
+        "<" | ">" | "<=" | ">=" => 7,
+        "<<" | ">>" => 8,
+        "+" | "-" => 9,
+        "*" | "/" | "%" => 10,
+        _ => return None,
+    })
+}
+
+fn apply(op: &str, left: Option<i64>, right: Option<i64>) -> Option<i64> {
+    // Logical operators are decided by one known side.
+    match (op, left, right) {
+        ("&&", Some(0), _) | ("&&", _, Some(0)) => return Some(0),
+        ("||", Some(value), _) | ("||", _, Some(value)) if value != 0 => return Some(1),
+        _ => {}
+    }
+    let (left, right) = (left?, right?);
+    Some(match op {
+        "||" => (left != 0 || right != 0) as i64,
+        "&&" => (left != 0 && right != 0) as i64,
+        "|" => left | right,
+        "^" => left ^ right,
+        "&" => left & right,
+        "==" => (left == right) as i64,
+        "!=" => (left != right) as i64,
+        "<" => (left < right) as i64,
+        ">" => (left > right) as i64,
+        "<=" => (left <= right) as i64,
+        ">=" => (left >= right) as i64,
+        "<<" => left.checked_shl(u32::try_from(right).ok()?)?,
+        ">>" => left.checked_shr(u32::try_from(right).ok()?)?,
+        "+" => left.wrapping_add(right),
+        "-" => left.wrapping_sub(right),
+        "*" => left.wrapping_mul(right),
+        "/" => left.checked_div(right)?,
+        "%" => left.checked_rem(right)?,
+        _ => return None,
+    })
+}
+
+fn parse_number(token: &str) -> Option<i64> {
+    let token = token.trim_end_matches(['u', 'U']);
+    if let Some(hex) = token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16).ok()
+    } else if token.len() > 1 && token.starts_with('0') {
+        i64::from_str_radix(&token[1..], 8).ok()
+    } else {
+        token.parse().ok()
+    }
+}
+
+/// The macro guarding the whole of a file, as in `#ifndef NAME`,
+/// `#define NAME`, .., `#endif` with nothing outside the block.
+pub(crate) fn include_guard(lines: &[String]) -> Option<&str> {
+    let mut code = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let (_, first) = code.next()?;
+    let name = identifier(source::directive(first, "ifndef")?);
+    let (_, second) = code.next()?;
+    if identifier(source::directive(second, "define")?) != name || name.is_empty() {
+        return None;
+    }
+
+    let mut depth = 1;
+    for (index, line) in code {
+        if ["if", "ifdef", "ifndef"]
+            .iter()
+            .any(|directive| source::directive(line, directive).is_some())
+        {
+            depth += 1;
+        } else if source::directive(line, "endif").is_some() {
+            depth -= 1;
+            if depth == 0 {
+                let rest = &lines[index + 1..];
+                return rest
+                    .iter()
+                    .all(|line| line.trim().is_empty())
+                    .then_some(name);
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+/// Severity of a glslang diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// A single message parsed from a glslang info log.
+///
+/// glslang reports messages as `ERROR: 0:12: 'foo' : undeclared identifier`,
+/// where `0` is the source string index and `12` the line within it. Messages
+/// that are not tied to a location, such as link errors, have no `source` or
+/// `line`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub source: Option<u32>,
+    pub line: Option<u32>,
+    /// The token glslang quoted as the cause of the message, if any.
+    pub token: Option<String>,
+    pub message: String,
+}
+
+// Ordered so that "INTERNAL ERROR: " is matched before "ERROR: ".
+const PREFIXES: &[(&str, Severity)] = &[
+    ("INTERNAL ERROR: ", Severity::Error),
+    ("UNIMPLEMENTED: ", Severity::Error),
+    ("ERROR: ", Severity::Error),
+    ("WARNING: ", Severity::Warning),
+    ("NOTE: ", Severity::Note),
+];
+
+impl Diagnostic {
+    /// Parses every diagnostic out of a glslang info log.
+    ///
+    /// Lines that don't start with a severity are treated as a continuation of
+    /// the previous message. The trailing "N compilation errors" summary is
+    /// dropped since it duplicates the individual errors.
+    pub fn parse_log(log: &str) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+        for line in log.lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+
+            match Diagnostic::parse_line(line) {
+                Some(diagnostic) => {
+                    if !diagnostic.is_summary() {
+                        diagnostics.push(diagnostic);
+                    }
+                }
+                None => {
+                    if let Some(last) = diagnostics.last_mut() {
+                        last.message.push('\n');
+                        last.message.push_str(line);
+                    }
+                }
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Parses the messages reported by the SPIR-V generator.
+    ///
+    /// Unlike the info logs, these are lower case and carry no location.
+    /// Lines without a severity, such as "TBD functionality", become notes.
+    pub fn parse_spirv_messages(messages: &str) -> Vec<Diagnostic> {
+        messages
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (severity, message) = if let Some(message) = line.strip_prefix("error: ") {
+                    (Severity::Error, message)
+                } else if let Some(message) = line.strip_prefix("warning: ") {
+                    (Severity::Warning, message)
+                } else {
+                    (Severity::Note, line)
+                };
+
+                Diagnostic {
+                    severity,
+                    source: None,
+                    line: None,
+                    token: None,
+                    message: message.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    fn parse_line(text: &str) -> Option<Diagnostic> {
+        let (rest, severity) = PREFIXES.iter().find_map(|(prefix, severity)| {
+            text.strip_prefix(prefix).map(|rest| (rest, *severity))
+        })?;
+
+        let mut source = None;
+        let mut line = None;
+        let mut rest = rest;
+        if let Some(end) = rest.find(": ") {
+            let mut location = rest[..end].splitn(2, ':');
+            let parsed_source = location.next().and_then(|s| s.parse().ok());
+            let parsed_line = location.next().and_then(|l| l.parse().ok());
+            if let (Some(s), Some(l)) = (parsed_source, parsed_line) {
+                source = Some(s);
+                line = Some(l);
+                rest = &rest[end + 2..];
+            }
+        }
+
+        let mut token = None;
+        if let Some(quoted) = rest.strip_prefix('\'') {
+            if let Some(end) = quoted.find("' : ") {
+                if end > 0 {
+                    token = Some(quoted[..end].to_string());
+                }
+                rest = &quoted[end + 4..];
+            }
+        }
+
+        Some(Diagnostic {
+            severity,
+            source,
+            line,
+            token,
+            message: rest.trim().to_string(),
+        })
+    }
+
+    fn is_summary(&self) -> bool {
+        self.line.is_none()
+            && self
+                .message
+                .ends_with("compilation errors.  No code generated.")
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        if let (Some(source), Some(line)) = (self.source, self.line) {
+            write!(f, "{}:{}: ", source, line)?;
+        }
+        if let Some(token) = &self.token {
+            write!(f, "'{}' : ", token)?;
+        }
+        f.write_str(&self.message)
+    }
+}
@@ -0,0 +1,159 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{Diagnostic, ReflectError, ShaderType, ValidationError};
+
+/// Error returned when a shader fails to compile.
+///
+/// Each variant corresponds to the glslang phase that failed. The info log and
+/// debug log are kept exactly as glslang reported them, alongside the
+/// diagnostics parsed from the info log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    /// `glslang_shader_preprocess` failed.
+    Preprocess {
+        info_log: String,
+        debug_log: String,
+        diagnostics: Vec<Diagnostic>,
+        /// Files pulled in through `#include` up to the failure, as in
+        /// [`CompileOutput::includes`](crate::CompileOutput::includes).
+        includes: Vec<String>,
+    },
+    /// `glslang_shader_parse` failed.
+    Parse {
+        info_log: String,
+        debug_log: String,
+        diagnostics: Vec<Diagnostic>,
+        /// Files pulled in through `#include` up to the failure, as in
+        /// [`CompileOutput::includes`](crate::CompileOutput::includes).
+        includes: Vec<String>,
+    },
+    /// `glslang_program_link` failed.
+    Link {
+        info_log: String,
+        debug_log: String,
+        diagnostics: Vec<Diagnostic>,
+        /// Files pulled in through `#include` up to the failure, as in
+        /// [`CompileOutput::includes`](crate::CompileOutput::includes).
+        includes: Vec<String>,
+    },
+    /// `glslang_program_SPIRV_generate` did not produce a module.
+    SpirvGeneration {
+        messages: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// A macro passed through the options can't be defined.
+    InvalidDefine { name: String, message: String },
+    /// The SPIR-V validator rejected the module generated for `stage`.
+    Validation {
+        stage: ShaderType,
+        error: ValidationError,
+    },
+    /// The module generated for `stage` couldn't be reflected.
+    Reflection {
+        stage: ShaderType,
+        error: ReflectError,
+    },
+    /// The options need a glslang function that can't be called on this
+    /// target, see [`CompileOptions`](crate::CompileOptions).
+    Unsupported { message: String },
+}
+
+impl CompileError {
+    /// The info log of the failed phase, or the SPIR-V generator messages.
+    pub fn info_log(&self) -> &str {
+        match self {
+            CompileError::Preprocess { info_log, .. }
+            | CompileError::Parse { info_log, .. }
+            | CompileError::Link { info_log, .. } => info_log,
+            CompileError::SpirvGeneration { messages, .. } => messages,
+            CompileError::InvalidDefine { message, .. } => message,
+            CompileError::Validation { error, .. } => &error.message,
+            CompileError::Reflection { error, .. } => &error.message,
+            CompileError::Unsupported { message } => message,
+        }
+    }
+
+    /// The diagnostics parsed from [`CompileError::info_log`].
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            CompileError::Preprocess { diagnostics, .. }
+            | CompileError::Parse { diagnostics, .. }
+            | CompileError::Link { diagnostics, .. }
+            | CompileError::SpirvGeneration { diagnostics, .. } => diagnostics,
+            CompileError::InvalidDefine { .. }
+            | CompileError::Validation { .. }
+            | CompileError::Reflection { .. }
+            | CompileError::Unsupported { .. } => &[],
+        }
+    }
+
+    /// The files pulled in through `#include`. Diagnostics in a file have its
+    /// index plus one as their source string.
+    pub fn includes(&self) -> &[String] {
+        match self {
+            CompileError::Preprocess { includes, .. }
+            | CompileError::Parse { includes, .. }
+            | CompileError::Link { includes, .. } => includes,
+            CompileError::SpirvGeneration { .. }
+            | CompileError::InvalidDefine { .. }
+            | CompileError::Validation { .. }
+            | CompileError::Reflection { .. }
+            | CompileError::Unsupported { .. } => &[],
+        }
+    }
+
+    /// The debug log of the failed phase, if glslang produces one for it.
+    pub fn debug_log(&self) -> Option<&str> {
+        match self {
+            CompileError::Preprocess { debug_log, .. }
+            | CompileError::Parse { debug_log, .. }
+            | CompileError::Link { debug_log, .. } => Some(debug_log),
+            CompileError::SpirvGeneration { .. }
+            | CompileError::InvalidDefine { .. }
+            | CompileError::Validation { .. }
+            | CompileError::Reflection { .. }
+            | CompileError::Unsupported { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = match self {
+            CompileError::Preprocess { .. } => "preprocessing",
+            CompileError::Parse { .. } => "parsing",
+            CompileError::Link { .. } => "linking",
+            CompileError::SpirvGeneration { .. } => "SPIR-V generation",
+            CompileError::InvalidDefine { name, message } => {
+                return write!(f, "invalid define '{}': {}", name, message);
+            }
+            CompileError::Validation { stage, error } => {
+                return write!(
+                    f,
+                    "SPIR-V validation of {:?} shader failed: {}",
+                    stage, error
+                );
+            }
+            CompileError::Reflection { stage, error } => {
+                return write!(
+                    f,
+                    "SPIR-V reflection of {:?} shader failed: {}",
+                    stage, error
+                );
+            }
+            CompileError::Unsupported { message } => {
+                return write!(f, "unsupported option: {}", message);
+            }
+        };
+        write!(f, "shader {} failed", phase)?;
+
+        let info_log = self.info_log().trim_end();
+        if !info_log.is_empty() {
+            write!(f, ":\n{}", info_log)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for CompileError {}
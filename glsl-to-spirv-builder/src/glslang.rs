@@ -0,0 +1,228 @@
+//! Safe wrappers around the shader and program objects of glslang's C
+//! interface, which free them when dropped.
+
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::os::raw::c_char;
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use crate::glslang_c_interface::*;
+use crate::{tshader, BindingClass, CompileError, CompileOptions, ShaderType};
+
+/// Keeps glslang's process-wide state initialized while alive.
+///
+/// Initializing builds glslang's built-in symbol tables, which is slow, so
+/// every user shares one instance through [`Process::acquire`].
+pub(crate) struct Process(());
+
+impl Process {
+    /// The live instance, initializing glslang if there is none.
+    pub fn acquire() -> Arc<Process> {
+        static CURRENT: Mutex<Weak<Process>> = Mutex::new(Weak::new());
+
+        let mut current = CURRENT.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(process) = current.upgrade() {
+            return process;
+        }
+        unsafe {
+            glslang_initialize_process();
+        }
+        let process = Arc::new(Process(()));
+        *current = Arc::downgrade(&process);
+        process
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        // glslang counts initializations itself, so this can't tear down the
+        // state of a newer instance acquired in the meantime.
+        unsafe {
+            glslang_finalize_process();
+        }
+    }
+}
+
+/// The glslang input for one shader, owning its source code.
+pub(crate) struct Input<'a> {
+    raw: glslang_input_t,
+    _code: CString,
+    _resource: PhantomData<&'a glslang_resource_t>,
+}
+
+impl<'a> Input<'a> {
+    pub fn new(
+        ty: ShaderType,
+        options: &CompileOptions,
+        resource: &'a glslang_resource_t,
+        code: &str,
+        messages: glslang_messages_t,
+    ) -> Self {
+        let code = CString::new(code).unwrap();
+        let raw = glslang_input_t {
+            language: options.source_language.source(),
+            stage: ty.stage(),
+            client: options.target_env.client(),
+            client_version: options.target_env.client_version(),
+            target_language: glslang_target_language_t_GLSLANG_TARGET_SPV,
+            target_language_version: options.spirv_version.target_language_version(),
+            code: code.as_ptr(),
+            default_version: options.default_version,
+            default_profile: options.default_profile.profile(),
+            force_default_version_and_profile: options.force_default_version_and_profile as i32,
+            forward_compatible: options.forward_compatible as i32,
+            messages: glslang_messages_t_GLSLANG_MSG_DEFAULT_BIT | messages,
+            resource,
+        };
+        Input {
+            raw,
+            _code: code,
+            _resource: PhantomData,
+        }
+    }
+}
+
+/// A single shader. glslang keeps pointers into the input, so the shader owns
+/// it.
+pub(crate) struct Shader<'a> {
+    raw: *mut glslang_shader_t,
+    input: Box<Input<'a>>,
+}
+
+impl<'a> Shader<'a> {
+    pub fn new(input: Input<'a>) -> Self {
+        let input = Box::new(input);
+        let raw = unsafe { glslang_shader_create(&input.raw) };
+        Shader { raw, input }
+    }
+
+    /// Sets the name of the entry point function. Only HLSL honours it.
+    pub fn set_entry_point(&mut self, name: &str) -> Result<(), CompileError> {
+        unsafe { tshader::entry_point(self.raw, name) }
+    }
+
+    pub fn set_source_entry_point(&mut self, name: &str) -> Result<(), CompileError> {
+        unsafe { tshader::source_entry_point(self.raw, name) }
+    }
+
+    pub fn set_auto_map_bindings(&mut self, map: bool) -> Result<(), CompileError> {
+        unsafe { tshader::auto_map_bindings(self.raw, map) }
+    }
+
+    pub fn set_auto_map_locations(&mut self, map: bool) -> Result<(), CompileError> {
+        unsafe { tshader::auto_map_locations(self.raw, map) }
+    }
+
+    pub fn set_shift_binding(
+        &mut self,
+        class: BindingClass,
+        shift: u32,
+    ) -> Result<(), CompileError> {
+        unsafe { tshader::shift_binding(self.raw, class.resource_type(), shift) }
+    }
+
+    pub fn set_resource_set_binding(&mut self, set: u32) -> Result<(), CompileError> {
+        unsafe { tshader::resource_set_binding(self.raw, set) }
+    }
+
+    pub fn preprocess(&mut self) -> bool {
+        unsafe { glslang_shader_preprocess(self.raw, &self.input.raw) != 0 }
+    }
+
+    pub fn parse(&mut self) -> bool {
+        unsafe { glslang_shader_parse(self.raw, &self.input.raw) != 0 }
+    }
+
+    pub fn preprocessed_code(&self) -> String {
+        unsafe { c_string(glslang_shader_get_preprocessed_code(self.raw)) }
+    }
+
+    pub fn info_log(&self) -> String {
+        unsafe { c_string(glslang_shader_get_info_log(self.raw)) }
+    }
+
+    pub fn info_debug_log(&self) -> String {
+        unsafe { c_string(glslang_shader_get_info_debug_log(self.raw)) }
+    }
+}
+
+impl Drop for Shader<'_> {
+    fn drop(&mut self) {
+        unsafe { glslang_shader_delete(self.raw) }
+    }
+}
+
+/// A program linking several shaders. glslang keeps pointers to the shaders,
+/// so the program owns them and deletes itself first.
+pub(crate) struct Program<'a> {
+    raw: *mut glslang_program_t,
+    shaders: Vec<Shader<'a>>,
+}
+
+impl<'a> Program<'a> {
+    pub fn new() -> Self {
+        Program {
+            raw: unsafe { glslang_program_create() },
+            shaders: Vec::new(),
+        }
+    }
+
+    pub fn add_shader(&mut self, shader: Shader<'a>) {
+        unsafe { glslang_program_add_shader(self.raw, shader.raw) }
+        self.shaders.push(shader);
+    }
+
+    pub fn link(&mut self, messages: glslang_messages_t) -> bool {
+        unsafe { glslang_program_link(self.raw, messages) != 0 }
+    }
+
+    /// Assigns bindings and locations, which the C interface doesn't do when
+    /// linking.
+    pub fn map_io(&mut self) -> Result<bool, CompileError> {
+        unsafe { tshader::map_io(self.raw) }
+    }
+
+    /// Generates the module of `ty`, appending it to [`spirv`](Self::spirv).
+    pub fn generate_spirv(&mut self, ty: ShaderType) {
+        unsafe { glslang_program_SPIRV_generate(self.raw, ty.stage()) }
+    }
+
+    /// Every module generated so far.
+    pub fn spirv(&self) -> &[u32] {
+        unsafe {
+            let size = glslang_program_SPIRV_get_size(self.raw) as usize;
+            if size == 0 {
+                return &[];
+            }
+            std::slice::from_raw_parts(glslang_program_SPIRV_get_ptr(self.raw), size)
+        }
+    }
+
+    pub fn spirv_messages(&self) -> String {
+        unsafe { c_string(glslang_program_SPIRV_get_messages(self.raw)) }
+    }
+
+    pub fn info_log(&self) -> String {
+        unsafe { c_string(glslang_program_get_info_log(self.raw)) }
+    }
+
+    pub fn info_debug_log(&self) -> String {
+        unsafe { c_string(glslang_program_get_info_debug_log(self.raw)) }
+    }
+}
+
+impl Drop for Program<'_> {
+    fn drop(&mut self) {
+        // The shaders are dropped after this.
+        unsafe { glslang_program_delete(self.raw) }
+    }
+}
+
+/// Copies a string owned by glslang, treating a null pointer as an empty string.
+unsafe fn c_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    }
+}
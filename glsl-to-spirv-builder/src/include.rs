@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::conditional::{self, Branch, Conditionals};
+use crate::source::{self, Version};
+use crate::{CompileError, CompileOptions, Diagnostic};
+
+// Bounds the nesting of distinct files. Cycles are reported on their own.
+const MAX_INCLUDE_DEPTH: usize = 64;
+
+/// The form of an `#include` directive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IncludeType {
+    /// `#include "file"`, looked up next to the including file first.
+    Relative,
+    /// `#include <file>`, looked up in the include directories only.
+    System,
+}
+
+/// The contents of an included file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedInclude {
+    /// Name identifying the file, such as its full path. It is passed back as
+    /// the requesting file for nested includes.
+    pub name: String,
+    pub content: String,
+}
+
+/// Resolves `#include` directives during preprocessing.
+pub trait IncludeResolver: Send + Sync {
+    /// Resolves `name` as included from the file named `requesting`.
+    ///
+    /// `depth` is 1 for includes in the compiled source itself and increases
+    /// by one for every level of nesting.
+    fn resolve(
+        &self,
+        name: &str,
+        ty: IncludeType,
+        requesting: &str,
+        depth: usize,
+    ) -> Result<ResolvedInclude, String>;
+}
+
+/// Resolves includes from the filesystem, searching a list of include
+/// directories in order.
+#[derive(Debug, Clone, Default)]
+pub struct FileIncludeResolver {
+    include_dirs: Vec<PathBuf>,
+}
+
+impl FileIncludeResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn include_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+}
+
+impl IncludeResolver for FileIncludeResolver {
+    fn resolve(
+        &self,
+        name: &str,
+        ty: IncludeType,
+        requesting: &str,
+        _depth: usize,
+    ) -> Result<ResolvedInclude, String> {
+        let mut candidates = Vec::new();
+        if ty == IncludeType::Relative {
+            if let Some(dir) = Path::new(requesting).parent() {
+                candidates.push(dir.join(name));
+            }
+        }
+        candidates.extend(self.include_dirs.iter().map(|dir| dir.join(name)));
+
+        for path in candidates {
+            if path.is_file() {
+                let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+                return Ok(ResolvedInclude {
+                    name: path.to_string_lossy().into_owned(),
+                    content,
+                });
+            }
+        }
+        Err("file not found".to_string())
+    }
+}
+
+/// Inlines every `#include` of `code`, recursively.
+///
+/// Includes in inactive `#if` branches are skipped, as are files with
+/// `#pragma once` that were included before and files whose include guard is
+/// defined. Including a file that is still being expanded otherwise is an
+/// error. Includes in branches depending on macros glslang predefines are
+/// inlined if they resolve, and otherwise left for glslang to report.
+///
+/// Each included file is given its own source string number, its index in
+/// `files` plus one, through `#line` directives, so diagnostics point into
+/// the right file. Files already in `files` keep their number.
+pub(crate) fn expand(
+    code: &str,
+    resolver: &dyn IncludeResolver,
+    options: &CompileOptions,
+    files: &mut Vec<String>,
+) -> Result<String, CompileError> {
+    let version = source::find_version(&source::code_lines(code));
+    let mut expander = Expander {
+        resolver,
+        options,
+        version,
+        files,
+        conditionals: Conditionals::new(options),
+        expanding: vec![options.source_name.clone()],
+        once: HashSet::new(),
+        guards: HashMap::new(),
+    };
+    expander.expand(code, &options.source_name, 0, 0)
+}
+
+struct Expander<'a> {
+    resolver: &'a dyn IncludeResolver,
+    options: &'a CompileOptions,
+    version: Option<Version>,
+    files: &'a mut Vec<String>,
+    conditionals: Conditionals,
+    /// Files being expanded, innermost last.
+    expanding: Vec<String>,
+    /// Files that contain `#pragma once`.
+    once: HashSet<String>,
+    /// Include guards of files that are entirely guarded.
+    guards: HashMap<String, String>,
+}
+
+impl Expander<'_> {
+    fn expand(
+        &mut self,
+        code: &str,
+        name: &str,
+        source_string: u32,
+        depth: usize,
+    ) -> Result<String, CompileError> {
+        let lines = source::code_lines(code);
+        let mut expanded = Vec::with_capacity(lines.len());
+
+        for (index, (line, code_line)) in code.split('\n').zip(&lines).enumerate() {
+            let state = self.conditionals.state();
+            let args = match source::directive(code_line, "include") {
+                Some(_) if state == Branch::Inactive => {
+                    expanded.push(String::new());
+                    continue;
+                }
+                Some(args) => args,
+                None if is_pragma_once(code_line) && state != Branch::Inactive => {
+                    if state == Branch::Active {
+                        self.once.insert(name.to_string());
+                    }
+                    expanded.push(String::new());
+                    continue;
+                }
+                None => {
+                    self.conditionals.line(code_line);
+                    expanded.push(line.to_string());
+                    continue;
+                }
+            };
+
+            let error = |token: &str, message: &str| {
+                let info_log = format!(
+                    "ERROR: {}:{}: '{}' : {}\n",
+                    source_string,
+                    index + 1,
+                    token,
+                    message
+                );
+                CompileError::Preprocess {
+                    diagnostics: Diagnostic::parse_log(&info_log),
+                    info_log,
+                    debug_log: String::new(),
+                    includes: self.files.clone(),
+                }
+            };
+
+            let (header, ty) = parse_header(args)
+                .ok_or_else(|| error("#include", "expected \"file\" or <file>"))?;
+            if depth >= MAX_INCLUDE_DEPTH {
+                return Err(error(header, "includes nested too deeply"));
+            }
+
+            let resolved = match self.resolver.resolve(header, ty, name, depth + 1) {
+                Ok(resolved) => resolved,
+                // The branch may be inactive, which only glslang can tell.
+                Err(_) if state == Branch::Unknown => {
+                    expanded.push(line.to_string());
+                    continue;
+                }
+                Err(err) => {
+                    return Err(error(
+                        header,
+                        &format!("could not resolve include: {}", err),
+                    ))
+                }
+            };
+            if self.is_skipped(&resolved.name) {
+                expanded.push(String::new());
+                continue;
+            }
+            if let Some(first) = self.expanding.iter().position(|f| *f == resolved.name) {
+                if state == Branch::Unknown {
+                    expanded.push(line.to_string());
+                    continue;
+                }
+                let mut cycle = self.expanding[first..].to_vec();
+                cycle.push(resolved.name);
+                return Err(error(
+                    header,
+                    &format!("recursive include: {}", cycle.join(" -> ")),
+                ));
+            }
+
+            let position = match self.files.iter().position(|file| *file == resolved.name) {
+                Some(position) => position,
+                None => {
+                    self.files.push(resolved.name.clone());
+                    self.files.len() - 1
+                }
+            };
+            let included = position as u32 + 1;
+
+            // Known before expanding, so guarded files may include each other.
+            if state == Branch::Active {
+                let lines = source::code_lines(&resolved.content);
+                if let Some(guard) = conditional::include_guard(&lines) {
+                    self.guards.insert(resolved.name.clone(), guard.to_string());
+                }
+            }
+            self.expanding.push(resolved.name.clone());
+            let content = self.expand(&resolved.content, &resolved.name, included, depth + 1)?;
+            self.expanding.pop();
+            expanded.push(format!(
+                "{}{}\n{}",
+                self.line_directive(1, included),
+                content,
+                self.line_directive(index + 2, source_string).trim_end()
+            ));
+        }
+
+        Ok(expanded.join("\n"))
+    }
+
+    fn is_skipped(&self, name: &str) -> bool {
+        let guarded = match self.guards.get(name) {
+            Some(guard) => self.conditionals.defined(guard) == Branch::Active,
+            None => false,
+        };
+        guarded || self.once.contains(name)
+    }
+
+    fn line_directive(&self, next_line: usize, source_string: u32) -> String {
+        source::line_directive(next_line, source_string, self.version, self.options)
+    }
+}
+
+fn is_pragma_once(code_line: &str) -> bool {
+    source::directive(code_line, "pragma") == Some("once")
+}
+
+fn parse_header(args: &str) -> Option<(&str, IncludeType)> {
+    if let Some(rest) = args.strip_prefix('"') {
+        let end = rest.find('"')?;
+        Some((&rest[..end], IncludeType::Relative))
+    } else if let Some(rest) = args.strip_prefix('<') {
+        let end = rest.find('>')?;
+        Some((&rest[..end], IncludeType::System))
+    } else {
+        None
+    }
+}
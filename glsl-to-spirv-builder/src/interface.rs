@@ -0,0 +1,253 @@
+//! Cross-stage interface checks.
+//!
+//! glslang links each stage of a program on its own, so it doesn't notice when
+//! a vertex output and a fragment input at the same location disagree. The
+//! generated modules are compared here instead.
+
+use std::collections::HashMap;
+
+use crate::spirv::{self, decoration, op, storage_class, Instruction, Types};
+use crate::{ShaderType, SpirvOutput};
+
+// Graphics stages in pipeline order.
+const PIPELINE: &[ShaderType] = &[
+    ShaderType::Vertex,
+    ShaderType::TessellationControl,
+    ShaderType::TessellationEvaluation,
+    ShaderType::Geometry,
+    ShaderType::Mesh,
+    ShaderType::Fragment,
+];
+
+/// One location (and component) of the interface, either a whole variable or
+/// a member of a block.
+struct Variable {
+    name: String,
+    location: u32,
+    component: u32,
+    ty: String,
+}
+
+/// Mismatches between the stages of a program, formatted like glslang's own
+/// link messages.
+#[derive(Default)]
+pub(crate) struct Mismatches {
+    /// Outputs and inputs of different types at the same location.
+    pub errors: Vec<String>,
+    /// Inputs no output writes. Their values are undefined, which Vulkan
+    /// allows.
+    pub warnings: Vec<String>,
+}
+
+/// Checks every pair of consecutive graphics stages.
+pub(crate) fn check(spirv: &HashMap<ShaderType, SpirvOutput>) -> Mismatches {
+    let stages: Vec<ShaderType> = PIPELINE
+        .iter()
+        .copied()
+        .filter(|stage| spirv.contains_key(stage))
+        .collect();
+
+    let mut mismatches = Mismatches::default();
+    for pair in stages.windows(2) {
+        let (producer, consumer) = (pair[0], pair[1]);
+        let outputs = variables(&spirv[&producer], storage_class::OUTPUT, producer);
+        let inputs = variables(&spirv[&consumer], storage_class::INPUT, consumer);
+        let linking = format!(
+            "Linking {} and {} stages",
+            stage_name(producer),
+            stage_name(consumer)
+        );
+
+        for input in &inputs {
+            let output = outputs
+                .iter()
+                .find(|o| o.location == input.location && o.component == input.component);
+
+            match output {
+                None => mismatches.warnings.push(format!(
+                    "WARNING: {}: No output for input '{}' at location {}, its value is undefined",
+                    linking, input.name, input.location
+                )),
+                Some(output) if output.ty != input.ty => mismatches.errors.push(format!(
+                    "ERROR: {}: Type mismatch at location {}: '{}' output '{}' vs '{}' input '{}'",
+                    linking, input.location, output.ty, output.name, input.ty, input.name
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+    mismatches
+}
+
+/// User-defined interface variables of `storage` class with an explicit
+/// location. Blocks are split into their members, each at its own location.
+/// Per-vertex arrays are unwrapped so they compare equal to the matching
+/// non-arrayed variable of the neighbouring stage. Patch variables are not
+/// checked.
+fn variables(words: &[u32], storage: u32, stage: ShaderType) -> Vec<Variable> {
+    let arrayed = match stage {
+        ShaderType::TessellationControl | ShaderType::Mesh => true,
+        ShaderType::TessellationEvaluation | ShaderType::Geometry => {
+            storage == storage_class::INPUT
+        }
+        _ => false,
+    };
+
+    // Members are keyed by their struct and index, variables by `(id, None)`.
+    let mut types = Types::default();
+    let mut names = HashMap::new();
+    let mut member_names = HashMap::new();
+    let mut locations = HashMap::new();
+    let mut components = HashMap::new();
+    let mut blocks = Vec::new();
+    let mut skipped = Vec::new();
+    let mut candidates = Vec::new();
+
+    for instruction in spirv::instructions(words) {
+        types.insert(instruction);
+        match (instruction.opcode, instruction.operands) {
+            (op::NAME, [id, name @ ..]) => {
+                names.insert(*id, spirv::string(name).0);
+            }
+            (op::MEMBER_NAME, [id, member, name @ ..]) => {
+                member_names.insert((*id, Some(*member)), spirv::string(name).0);
+            }
+            (op::DECORATE, [id, decoration::LOCATION, location, ..]) => {
+                locations.insert((*id, None), *location);
+            }
+            (op::DECORATE, [id, decoration::COMPONENT, component, ..]) => {
+                components.insert((*id, None), *component);
+            }
+            (op::MEMBER_DECORATE, [id, member, decoration::LOCATION, location, ..]) => {
+                locations.insert((*id, Some(*member)), *location);
+            }
+            (op::MEMBER_DECORATE, [id, member, decoration::COMPONENT, component, ..]) => {
+                components.insert((*id, Some(*member)), *component);
+            }
+            (op::DECORATE, [id, decoration::BLOCK, ..]) => blocks.push(*id),
+            (op::DECORATE, [id, decoration::BUILT_IN, ..])
+            | (op::DECORATE, [id, decoration::PATCH, ..]) => skipped.push((*id, None)),
+            (op::MEMBER_DECORATE, [id, member, decoration::BUILT_IN, ..])
+            | (op::MEMBER_DECORATE, [id, member, decoration::PATCH, ..]) => {
+                skipped.push((*id, Some(*member)))
+            }
+            (op::VARIABLE, [pointer, id, class, ..]) if *class == storage => {
+                candidates.push((*pointer, *id));
+            }
+            _ => {}
+        }
+    }
+
+    let mut variables = Vec::new();
+    for (pointer, id) in candidates {
+        if skipped.contains(&(id, None)) {
+            continue;
+        }
+        let mut ty = match types.pointee(pointer) {
+            Some(ty) => ty,
+            None => continue,
+        };
+        if arrayed {
+            ty = match types.element(ty) {
+                Some(ty) => ty,
+                None => continue,
+            };
+        }
+        let mut location = locations.get(&(id, None)).copied();
+
+        let members = match types.get(ty) {
+            Some(Instruction {
+                opcode: op::TYPE_STRUCT,
+                operands: [_, members @ ..],
+            }) if blocks.contains(&ty) => members,
+            _ => {
+                if let Some(location) = location {
+                    variables.push(Variable {
+                        name: names.get(&id).cloned().unwrap_or_default(),
+                        location,
+                        component: components.get(&(id, None)).copied().unwrap_or(0),
+                        ty: types.describe(ty),
+                    });
+                }
+                continue;
+            }
+        };
+
+        // Members without a location of their own follow the previous one.
+        let block = names.get(&ty).cloned().unwrap_or_default();
+        for (index, member) in members.iter().enumerate() {
+            let key = (ty, Some(index as u32));
+            if skipped.contains(&key) {
+                continue;
+            }
+            if let Some(member_location) = locations.get(&key) {
+                location = Some(*member_location);
+            }
+            let member_location = match location {
+                Some(location) => location,
+                None => continue,
+            };
+            variables.push(Variable {
+                name: format!(
+                    "{}.{}",
+                    block,
+                    member_names.get(&key).map_or("", String::as_str)
+                ),
+                location: member_location,
+                component: components.get(&key).copied().unwrap_or(0),
+                ty: types.describe(*member),
+            });
+            location = Some(member_location + location_count(&types, *member));
+        }
+    }
+    variables
+}
+
+/// Number of locations a variable of type `id` occupies.
+fn location_count(types: &Types, id: u32) -> u32 {
+    match types.get(id) {
+        // 64-bit vectors of three or four components take two locations.
+        Some(Instruction {
+            opcode: op::TYPE_VECTOR,
+            operands: [_, component, count, ..],
+        }) => match types.get(*component) {
+            Some(Instruction {
+                operands: [_, 64, ..],
+                ..
+            }) if *count > 2 => 2,
+            _ => 1,
+        },
+        Some(Instruction {
+            opcode: op::TYPE_MATRIX,
+            operands: [_, column, columns, ..],
+        }) => columns * location_count(types, *column),
+        Some(Instruction {
+            opcode: op::TYPE_ARRAY,
+            operands: [_, element, length, ..],
+        }) => types.constant(*length).unwrap_or(1) * location_count(types, *element),
+        Some(Instruction {
+            opcode: op::TYPE_STRUCT,
+            operands: [_, members @ ..],
+        }) => members.iter().map(|m| location_count(types, *m)).sum(),
+        _ => 1,
+    }
+}
+
+fn stage_name(stage: ShaderType) -> &'static str {
+    match stage {
+        ShaderType::Vertex => "vertex",
+        ShaderType::Fragment => "fragment",
+        ShaderType::Geometry => "geometry",
+        ShaderType::TessellationControl => "tessellation control",
+        ShaderType::TessellationEvaluation => "tessellation evaluation",
+        ShaderType::Compute => "compute",
+        ShaderType::Task => "task",
+        ShaderType::Mesh => "mesh",
+        ShaderType::RayGeneration => "ray generation",
+        ShaderType::Intersection => "intersection",
+        ShaderType::AnyHit => "any hit",
+        ShaderType::ClosestHit => "closest hit",
+        ShaderType::Miss => "miss",
+        ShaderType::Callable => "callable",
+    }
+}
@@ -7,157 +7,516 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
-use std::ffi::{CStr, CString};
-use std::os::raw::c_char;
-
+use std::collections::HashMap;
+
+mod cache;
+mod compiler;
+mod conditional;
+mod diagnostic;
+mod error;
+mod glslang;
 mod glslang_c_interface;
-
+mod include;
+mod interface;
+mod limits;
+mod options;
+mod reflect;
+mod source;
+mod spirv;
+mod tools;
+mod tshader;
+
+pub use cache::{CacheStats, ShaderCache};
+pub use compiler::{CompileJob, Compiler};
+pub use diagnostic::{Diagnostic, Severity};
+pub use error::CompileError;
 use glslang_c_interface::*;
+pub use include::{FileIncludeResolver, IncludeResolver, IncludeType, ResolvedInclude};
+pub use limits::{ParseLimitsError, ResourceLimits};
+pub use options::{BindingClass, CompileOptions, Profile, SourceLanguage, SpirvVersion, TargetEnv};
+pub use reflect::{
+    reflect, Block, BlockMember, DescriptorBinding, DescriptorKind, EntryPoint, InterfaceVariable,
+    PushConstantRange, ReflectError, Reflection, Resource, ResourceKind, SpecializationConstant,
+};
+pub use tools::{
+    assemble, assemble_with_version, disassemble, disassemble_with_options, validate,
+    AssembleError, DisassembleError, DisassembleOptions, ValidationError,
+};
 
 pub type SpirvOutput = Vec<u32>;
 
+/// A successfully compiled shader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileOutput {
+    pub spirv: SpirvOutput,
+    /// Name of the SPIR-V entry point, or `None` if glslang didn't find the
+    /// entry point function.
+    pub entry_point: Option<String>,
+    /// Resources and interface variables of the module.
+    pub reflection: Reflection,
+    /// Bindings and locations assigned by
+    /// [`auto_map_bindings`](CompileOptions::auto_map_bindings) and
+    /// [`auto_map_locations`](CompileOptions::auto_map_locations).
+    pub assignments: Vec<Assignment>,
+    /// Warnings and notes from the shader info log, the program info log and
+    /// the SPIR-V generator, in that order.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Every file pulled in through `#include`. Diagnostics in a file have
+    /// its index plus one as their source string.
+    pub includes: Vec<String>,
+}
+
+/// A successfully linked multi-stage program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramOutput {
+    /// One SPIR-V module per stage.
+    pub spirv: HashMap<ShaderType, SpirvOutput>,
+    /// Name of the SPIR-V entry point of every stage that has one.
+    pub entry_points: HashMap<ShaderType, String>,
+    /// Resources and interface variables of every stage.
+    pub reflection: HashMap<ShaderType, Reflection>,
+    /// Bindings and locations assigned by
+    /// [`auto_map_bindings`](CompileOptions::auto_map_bindings) and
+    /// [`auto_map_locations`](CompileOptions::auto_map_locations).
+    pub assignments: Vec<Assignment>,
+    /// Warnings and notes from every shader, the program and the SPIR-V
+    /// generator.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Every file pulled in through `#include` by any of the shaders.
+    pub includes: Vec<String>,
+}
+
+/// A binding or location assigned to a GLSL declaration that didn't have one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Assignment {
+    /// A uniform block, storage block or opaque uniform, named by its block
+    /// name or variable name.
+    Binding {
+        stage: ShaderType,
+        name: String,
+        set: u32,
+        binding: u32,
+    },
+    /// An input or output, named like in [`InterfaceVariable`].
+    Location {
+        stage: ShaderType,
+        name: String,
+        location: u32,
+    },
+}
+
 pub fn compile(
     code: &str,
     ty: ShaderType,
     shader_defs: Option<&[String]>,
-) -> Result<SpirvOutput, String> {
-    compile_inner(Some((code, ty)), shader_defs)
+) -> Result<CompileOutput, CompileError> {
+    let mut options = CompileOptions::new();
+    if let Some(defs) = shader_defs {
+        options = options.shader_defs(defs);
+    }
+    compile_with_options(code, ty, &options)
+}
+
+pub fn compile_with_options(
+    code: &str,
+    ty: ShaderType,
+    options: &CompileOptions,
+) -> Result<CompileOutput, CompileError> {
+    Compiler::shared().compile(code, ty, options)
+}
+
+fn compile_shader(
+    code: &str,
+    ty: ShaderType,
+    options: &CompileOptions,
+) -> Result<CompileOutput, CompileError> {
+    let mut output = compile_inner(Some((code, ty)), options)?;
+    Ok(CompileOutput {
+        spirv: output.spirv.remove(&ty).unwrap_or_default(),
+        entry_point: output.entry_points.remove(&ty),
+        reflection: output.reflection.remove(&ty).unwrap_or_default(),
+        assignments: output.assignments,
+        diagnostics: output.diagnostics,
+        includes: output.includes,
+    })
+}
+
+pub fn compile_batch(jobs: &[CompileJob<'_>]) -> Vec<Result<CompileOutput, CompileError>> {
+    compile_batch_with_options(jobs, &CompileOptions::new())
 }
 
-// Eventually the API will look like this, with an iterator for multiple shader stages.
-// However for the moment GLSLang doesn't like that, so we only pass one shader at a time.
-fn compile_inner<'a, I>(shaders: I, shader_defs: Option<&[String]>) -> Result<SpirvOutput, String>
+/// Compiles independent shaders on several threads.
+///
+/// Every job is compiled with `options` plus its own definitions. The results
+/// are in the order of `jobs`, and a failing job doesn't stop the others.
+pub fn compile_batch_with_options(
+    jobs: &[CompileJob<'_>],
+    options: &CompileOptions,
+) -> Vec<Result<CompileOutput, CompileError>> {
+    Compiler::shared().compile_batch(jobs, options)
+}
+
+impl ProgramOutput {
+    /// The stages the program has SPIR-V for.
+    pub fn stages(&self) -> ShaderStages {
+        self.spirv.keys().copied().collect()
+    }
+}
+
+/// Compiles several stages into a single program.
+///
+/// All shaders are linked together, so mismatches between the stages are
+/// reported as [`CompileError::Link`]. One SPIR-V module is produced per stage.
+pub fn compile_program(
+    shaders: &[(&str, ShaderType)],
+    options: &CompileOptions,
+) -> Result<ProgramOutput, CompileError> {
+    Compiler::shared().compile_program(shaders, options)
+}
+
+fn compile_inner<'a, I>(shaders: I, options: &CompileOptions) -> Result<ProgramOutput, CompileError>
 where
     I: IntoIterator<Item = (&'a str, ShaderType)>,
 {
-    let mut preamble = String::new();
-    if let Some(defs) = shader_defs {
-        for def in defs {
-            preamble.push_str("#define ");
-
-            let mut def = def.clone();
-            if let Some(end) = def.find('\n') {
-                def.truncate(end);
+    let preamble = preamble(options)?;
+    let resource = options.resource_limits.resource();
+
+    let mut spirv = HashMap::new();
+    let mut entry_points = HashMap::new();
+    let mut diagnostics = Vec::new();
+    let mut includes = Vec::new();
+    let mut declarations = Vec::new();
+
+    let mut program = glslang::Program::new();
+    let mut stages = Vec::new();
+
+    for (source, ty) in shaders.into_iter() {
+        let source = prepare_source(source, options, &preamble, &mut includes)?;
+        if options.source_language == SourceLanguage::Glsl && options.maps_io() {
+            declarations.push((ty, source::declarations(&source)));
+        }
+        let input = glslang::Input::new(ty, options, &resource, &source, options.messages());
+
+        let mut shader = glslang::Shader::new(input);
+        // GLSL always starts at `main`, see `preamble`.
+        if options.source_language == SourceLanguage::Hlsl {
+            shader.set_entry_point(&options.entry_point)?;
+            if let Some(name) = &options.source_entry_point {
+                shader.set_source_entry_point(name)?;
+            }
+        }
+        if options.maps_io() {
+            shader.set_auto_map_bindings(options.auto_map_bindings)?;
+            shader.set_auto_map_locations(options.auto_map_locations)?;
+            for (class, shift) in &options.binding_shifts {
+                shader.set_shift_binding(*class, *shift)?;
             }
+            if let Some(set) = options.resource_set_binding() {
+                shader.set_resource_set_binding(set)?;
+            }
+        }
 
-            let def = def.replacen('=', " ", 1);
+        if !shader.preprocess() {
+            let info_log = shader.info_log();
+            return Err(CompileError::Preprocess {
+                diagnostics: Diagnostic::parse_log(&info_log),
+                info_log,
+                debug_log: shader.info_debug_log(),
+                includes,
+            });
+        }
+        if !shader.parse() {
+            let info_log = shader.info_log();
+            return Err(CompileError::Parse {
+                diagnostics: Diagnostic::parse_log(&info_log),
+                info_log,
+                debug_log: shader.info_debug_log(),
+                includes,
+            });
+        }
 
-            preamble.push_str(&def);
-            preamble.push('\n');
+        diagnostics.extend(Diagnostic::parse_log(&shader.info_log()));
+
+        program.add_shader(shader);
+        if !stages.contains(&ty) {
+            stages.push(ty);
         }
     }
 
-    let mut data = Vec::new();
-
-    unsafe {
-        glslang_initialize_process();
+    // The C interface links without mapping.
+    if !program.link(options.messages()) || (options.maps_io() && !program.map_io()?) {
+        let info_log = program.info_log();
+        return Err(CompileError::Link {
+            diagnostics: Diagnostic::parse_log(&info_log),
+            info_log,
+            debug_log: program.info_debug_log(),
+            includes,
+        });
+    }
 
-        for (source, ty) in shaders.into_iter() {
-            let stage = match ty {
-                ShaderType::Vertex => glslang_stage_t_GLSLANG_STAGE_VERTEX,
-                ShaderType::Fragment => glslang_stage_t_GLSLANG_STAGE_FRAGMENT,
-                ShaderType::Geometry => glslang_stage_t_GLSLANG_STAGE_GEOMETRY,
-                ShaderType::TessellationControl => glslang_stage_t_GLSLANG_STAGE_TESSCONTROL,
-                ShaderType::TessellationEvaluation => glslang_stage_t_GLSLANG_STAGE_TESSEVALUATION,
-                ShaderType::Compute => glslang_stage_t_GLSLANG_STAGE_COMPUTE,
-            };
+    diagnostics.extend(Diagnostic::parse_log(&program.info_log()));
 
-            let mut source = String::from(source);
-            if shader_defs.is_some() {
-                if let Some(version) = source.find(r##"#version"##) {
-                    if let Some(newline) = &source[version..].find('\n') {
-                        source.insert_str(version + newline + 1, &preamble);
-                    }
-                }
-            }
+    // glslang appends each generated module to the same buffer.
+    let mut generated = 0;
+    for ty in stages {
+        program.generate_spirv(ty);
 
-            let c_str = CString::new(source.as_str()).unwrap();
-            let code: *const c_char = c_str.as_ptr();
-
-            let input = &glslang_input_t {
-                language: glslang_source_t_GLSLANG_SOURCE_GLSL,
-                stage,
-                client: glslang_client_t_GLSLANG_CLIENT_VULKAN,
-                client_version: glslang_target_client_version_t_GLSLANG_TARGET_VULKAN_1_0,
-                target_language: glslang_target_language_t_GLSLANG_TARGET_SPV,
-                target_language_version: glslang_target_language_version_t_GLSLANG_TARGET_SPV_1_0,
-                code,
-                default_version: 100,
-                default_profile: glslang_profile_t_GLSLANG_NO_PROFILE,
-                force_default_version_and_profile: 0,
-                forward_compatible: 0,
-                messages: glslang_messages_t_GLSLANG_MSG_DEFAULT_BIT
-                    | glslang_messages_t_GLSLANG_MSG_SPV_RULES_BIT
-                    | glslang_messages_t_GLSLANG_MSG_VULKAN_RULES_BIT,
-                resource: DEFAULT_RESOURCE_LIMITS,
+        let messages = program.spirv_messages();
+        let words = &program.spirv()[generated..];
+        if words.is_empty() {
+            return Err(CompileError::SpirvGeneration {
+                diagnostics: Diagnostic::parse_spirv_messages(&messages),
+                messages,
+            });
+        }
+        diagnostics.extend(Diagnostic::parse_spirv_messages(&messages));
+
+        // glslang names GLSL entry points `main` regardless.
+        let module =
+            if options.source_language == SourceLanguage::Glsl && options.entry_point != "main" {
+                spirv::rename_entry_points(words, &options.entry_point)
+            } else {
+                words.to_vec()
             };
+        if let Some(name) = spirv::entry_point(&module) {
+            entry_points.insert(ty, name);
+        }
+        generated += words.len();
+        spirv.insert(ty, module);
+    }
 
-            let shader = glslang_shader_create(input);
+    let mismatches = interface::check(&spirv);
+    diagnostics.extend(Diagnostic::parse_log(&mismatches.warnings.join("\n")));
+    if !mismatches.errors.is_empty() {
+        let info_log = mismatches.errors.join("\n");
+        return Err(CompileError::Link {
+            diagnostics: Diagnostic::parse_log(&info_log),
+            info_log,
+            debug_log: String::new(),
+            includes,
+        });
+    }
 
-            if glslang_shader_preprocess(shader, input) == 0 {
-                let c_info: &CStr = CStr::from_ptr(glslang_shader_get_info_log(shader));
-                let c_debug: &CStr = CStr::from_ptr(glslang_shader_get_info_debug_log(shader));
+    if options.validate {
+        for (ty, module) in &spirv {
+            tools::validate(module, options.target_env)
+                .map_err(|error| CompileError::Validation { stage: *ty, error })?;
+        }
+    }
 
-                let mut error = String::from("glslang_shader_preprocess:\n");
-                error.push_str(&format!("Info log:\n{}\n", c_info.to_str().unwrap()));
-                error.push_str(&format!("Debug log:\n{}\n", c_debug.to_str().unwrap()));
+    let mut reflection = HashMap::new();
+    for (ty, module) in &spirv {
+        let reflected = reflect::reflect(module)
+            .map_err(|error| CompileError::Reflection { stage: *ty, error })?;
+        reflection.insert(*ty, reflected);
+    }
 
-                return Err(error);
-            }
-            if glslang_shader_parse(shader, input) == 0 {
-                let c_info: &CStr = CStr::from_ptr(glslang_shader_get_info_log(shader));
-                let c_debug: &CStr = CStr::from_ptr(glslang_shader_get_info_debug_log(shader));
+    let mut assignments = Vec::new();
+    for (ty, declarations) in &declarations {
+        if let Some(reflection) = reflection.get(ty) {
+            assignments.extend(assigned(*ty, declarations, reflection, options));
+        }
+    }
 
-                let mut error = String::from("glslang_shader_parse:\n");
-                error.push_str(&format!("Info log:\n{}\n", c_info.to_str().unwrap()));
-                error.push_str(&format!("Debug log:\n{}\n", c_debug.to_str().unwrap()));
+    Ok(ProgramOutput {
+        spirv,
+        entry_points,
+        reflection,
+        assignments,
+        diagnostics,
+        includes,
+    })
+}
 
-                return Err(error);
+/// The bindings and locations glslang assigned to `declarations`, looked up
+/// by name in the reflection of the stage.
+fn assigned(
+    stage: ShaderType,
+    declarations: &[source::Declaration],
+    reflection: &Reflection,
+    options: &CompileOptions,
+) -> Vec<Assignment> {
+    let mut assignments = Vec::new();
+    for declaration in declarations {
+        for name in &declaration.names {
+            match declaration.storage {
+                source::Storage::Resource
+                    if options.auto_map_bindings && !declaration.has_layout("binding") =>
+                {
+                    let blocks = reflection
+                        .uniform_blocks
+                        .iter()
+                        .chain(&reflection.storage_buffers)
+                        .map(|b| (&b.name, b.set, b.binding));
+                    let resources = reflection
+                        .resources
+                        .iter()
+                        .map(|r| (&r.name, r.set, r.binding));
+                    if let Some((_, set, binding)) =
+                        blocks.chain(resources).find(|(n, _, _)| *n == name)
+                    {
+                        assignments.push(Assignment::Binding {
+                            stage,
+                            name: name.clone(),
+                            set,
+                            binding,
+                        });
+                    }
+                }
+                source::Storage::Input | source::Storage::Output
+                    if options.auto_map_locations && !declaration.has_layout("location") =>
+                {
+                    let variables = if declaration.storage == source::Storage::Input {
+                        &reflection.inputs
+                    } else {
+                        &reflection.outputs
+                    };
+                    let member = format!("{}.", name);
+                    for variable in variables {
+                        if variable.name == *name || variable.name.starts_with(&member) {
+                            assignments.push(Assignment::Location {
+                                stage,
+                                name: variable.name.clone(),
+                                location: variable.location,
+                            });
+                        }
+                    }
+                }
+                _ => {}
             }
+        }
+    }
+    assignments
+}
 
-            let program = glslang_program_create();
-            glslang_program_add_shader(program, shader);
-
-            if glslang_program_link(
-                program,
-                glslang_messages_t_GLSLANG_MSG_SPV_RULES_BIT
-                    | glslang_messages_t_GLSLANG_MSG_VULKAN_RULES_BIT,
-            ) == 0
-            {
-                let c_info: &CStr = CStr::from_ptr(glslang_program_get_info_log(program));
-                let c_debug: &CStr = CStr::from_ptr(glslang_program_get_info_debug_log(program));
-
-                let mut error = String::from("glslang_program_link:\n");
-                error.push_str(&format!("Info log:\n{}\n", c_info.to_str().unwrap()));
-                error.push_str(&format!("Debug log:\n{}\n", c_debug.to_str().unwrap()));
+/// Runs only the preprocessor, returning the expanded source.
+///
+/// Definitions are substituted and includes inlined, while `#line` directives
+/// are kept so the output can still be mapped back to the original files.
+pub fn preprocess(
+    code: &str,
+    ty: ShaderType,
+    shader_defs: Option<&[String]>,
+) -> Result<String, CompileError> {
+    let mut options = CompileOptions::new();
+    if let Some(defs) = shader_defs {
+        options = options.shader_defs(defs);
+    }
+    preprocess_with_options(code, ty, &options)
+}
 
-                return Err(error);
-            }
+pub fn preprocess_with_options(
+    code: &str,
+    ty: ShaderType,
+    options: &CompileOptions,
+) -> Result<String, CompileError> {
+    Compiler::shared().preprocess(code, ty, options)
+}
 
-            glslang_program_SPIRV_generate(program, input.stage);
+fn preprocess_shader(
+    code: &str,
+    ty: ShaderType,
+    options: &CompileOptions,
+) -> Result<String, CompileError> {
+    let mut includes = Vec::new();
+    let source = prepare_source(code, options, &preamble(options)?, &mut includes)?;
+    let messages = options.messages() | glslang_messages_t_GLSLANG_MSG_ONLY_PREPROCESSOR_BIT;
+    let resource = options.resource_limits.resource();
+
+    let input = glslang::Input::new(ty, options, &resource, &source, messages);
+    let mut shader = glslang::Shader::new(input);
+    if !shader.preprocess() {
+        let info_log = shader.info_log();
+        return Err(CompileError::Preprocess {
+            diagnostics: Diagnostic::parse_log(&info_log),
+            info_log,
+            debug_log: shader.info_debug_log(),
+            includes,
+        });
+    }
+    Ok(shader.preprocessed_code())
+}
 
-            if glslang_program_SPIRV_get_messages(program) != std::ptr::null() {
-                let c_messages: &CStr = CStr::from_ptr(glslang_program_SPIRV_get_messages(program));
+/// `#define` lines for every definition of `options`.
+///
+/// glslang insists on `main` as the GLSL entry point function, so a GLSL
+/// source entry point is defined to `main` as well.
+fn preamble(options: &CompileOptions) -> Result<String, CompileError> {
+    let mut preamble = String::new();
+    for (name, value) in &options.defines {
+        let invalid = |message: &str| CompileError::InvalidDefine {
+            name: name.clone(),
+            message: message.to_string(),
+        };
+
+        if !is_identifier(name) {
+            return Err(invalid("not a valid identifier"));
+        }
+        if name.starts_with("GL_") || name.contains("__") {
+            return Err(invalid(
+                "names starting with GL_ or containing __ are reserved",
+            ));
+        }
 
-                println!("{:?}", c_messages);
+        preamble.push_str("#define ");
+        preamble.push_str(name);
+        if let Some(value) = value {
+            if value.contains(&['\n', '\r'][..]) {
+                return Err(invalid("values can't span multiple lines"));
             }
+            preamble.push(' ');
+            preamble.push_str(value);
+        }
+        preamble.push('\n');
+    }
 
-            let size = glslang_program_SPIRV_get_size(program) as usize;
-            let ptr = glslang_program_SPIRV_get_ptr(program) as *mut u32;
-            data = std::slice::from_raw_parts(ptr, size).to_vec();
-
-            glslang_program_delete(program);
-            glslang_shader_delete(shader);
+    if let (SourceLanguage::Glsl, Some(name)) =
+        (options.source_language, &options.source_entry_point)
+    {
+        if !is_identifier(name) {
+            return Err(CompileError::InvalidDefine {
+                name: name.clone(),
+                message: "source entry point is not a valid identifier".to_string(),
+            });
+        }
+        if name != "main" {
+            preamble.push_str(&format!("#define {} main\n", name));
         }
+    }
+    Ok(preamble)
+}
 
-        glslang_finalize_process();
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => {
+            (first.is_ascii_alphabetic() || first == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
     }
+}
 
-    return Ok(data);
+/// Inlines includes and inserts the definitions of `preamble`.
+fn prepare_source(
+    source: &str,
+    options: &CompileOptions,
+    preamble: &str,
+    includes: &mut Vec<String>,
+) -> Result<String, CompileError> {
+    let source = match &options.include_resolver {
+        Some(resolver) => include::expand(source, &*resolver.0, options, includes)?,
+        None => String::from(source),
+    };
+    if preamble.is_empty() {
+        Ok(source)
+    } else {
+        Ok(source::insert_preamble(&source, preamble, options))
+    }
 }
 
 /// Type of shader.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ShaderType {
     Vertex,
     Fragment,
@@ -165,111 +524,139 @@ pub enum ShaderType {
     TessellationControl,
     TessellationEvaluation,
     Compute,
+    Task,
+    Mesh,
+    RayGeneration,
+    Intersection,
+    AnyHit,
+    ClosestHit,
+    Miss,
+    Callable,
 }
 
-// values copied from glslang default resource limits
-const DEFAULT_RESOURCE_LIMITS: &glslang_resource_t = &glslang_resource_t {
-    max_lights: 32,
-    max_clip_planes: 6,
-    max_texture_units: 32,
-    max_texture_coords: 32,
-    max_vertex_attribs: 64,
-    max_vertex_uniform_components: 4096,
-    max_varying_floats: 64,
-    max_vertex_texture_image_units: 32,
-    max_combined_texture_image_units: 80,
-    max_texture_image_units: 32,
-    max_fragment_uniform_components: 4096,
-    max_draw_buffers: 32,
-    max_vertex_uniform_vectors: 128,
-    max_varying_vectors: 8,
-    max_fragment_uniform_vectors: 16,
-    max_vertex_output_vectors: 16,
-    max_fragment_input_vectors: 15,
-    min_program_texel_offset: -8,
-    max_program_texel_offset: 7,
-    max_clip_distances: 8,
-    max_compute_work_group_count_x: 65535,
-    max_compute_work_group_count_y: 65535,
-    max_compute_work_group_count_z: 65535,
-    max_compute_work_group_size_x: 1024,
-    max_compute_work_group_size_y: 1024,
-    max_compute_work_group_size_z: 64,
-    max_compute_uniform_components: 1024,
-    max_compute_texture_image_units: 16,
-    max_compute_image_uniforms: 8,
-    max_compute_atomic_counters: 8,
-    max_compute_atomic_counter_buffers: 1,
-    max_varying_components: 60,
-    max_vertex_output_components: 64,
-    max_geometry_input_components: 64,
-    max_geometry_output_components: 128,
-    max_fragment_input_components: 128,
-    max_image_units: 8,
-    max_combined_image_units_and_fragment_outputs: 8,
-    max_combined_shader_output_resources: 8,
-    max_image_samples: 0,
-    max_vertex_image_uniforms: 0,
-    max_tess_control_image_uniforms: 0,
-    max_tess_evaluation_image_uniforms: 0,
-    max_geometry_image_uniforms: 0,
-    max_fragment_image_uniforms: 8,
-    max_combined_image_uniforms: 8,
-    max_geometry_texture_image_units: 16,
-    max_geometry_output_vertices: 256,
-    max_geometry_total_output_components: 1024,
-    max_geometry_uniform_components: 1024,
-    max_geometry_varying_components: 64,
-    max_tess_control_input_components: 128,
-    max_tess_control_output_components: 128,
-    max_tess_control_texture_image_units: 16,
-    max_tess_control_uniform_components: 1024,
-    max_tess_control_total_output_components: 4096,
-    max_tess_evaluation_input_components: 128,
-    max_tess_evaluation_output_components: 128,
-    max_tess_evaluation_texture_image_units: 16,
-    max_tess_evaluation_uniform_components: 1024,
-    max_tess_patch_components: 120,
-    max_patch_vertices: 32,
-    max_tess_gen_level: 64,
-    max_viewports: 16,
-    max_vertex_atomic_counters: 0,
-    max_tess_control_atomic_counters: 0,
-    max_tess_evaluation_atomic_counters: 0,
-    max_geometry_atomic_counters: 0,
-    max_fragment_atomic_counters: 8,
-    max_combined_atomic_counters: 8,
-    max_atomic_counter_bindings: 1,
-    max_vertex_atomic_counter_buffers: 0,
-    max_tess_control_atomic_counter_buffers: 0,
-    max_tess_evaluation_atomic_counter_buffers: 0,
-    max_geometry_atomic_counter_buffers: 0,
-    max_fragment_atomic_counter_buffers: 1,
-    max_combined_atomic_counter_buffers: 1,
-    max_atomic_counter_buffer_size: 16384,
-    max_transform_feedback_buffers: 4,
-    max_transform_feedback_interleaved_components: 64,
-    max_cull_distances: 8,
-    max_combined_clip_and_cull_distances: 8,
-    max_samples: 4,
-    max_mesh_output_vertices_nv: 256,
-    max_mesh_output_primitives_nv: 512,
-    max_mesh_work_group_size_x_nv: 32,
-    max_mesh_work_group_size_y_nv: 1,
-    max_mesh_work_group_size_z_nv: 1,
-    max_task_work_group_size_x_nv: 32,
-    max_task_work_group_size_y_nv: 1,
-    max_task_work_group_size_z_nv: 1,
-    max_mesh_view_count_nv: 4,
-    limits: glslang_limits_s {
-        non_inductive_for_loops: true,
-        while_loops: true,
-        do_while_loops: true,
-        general_uniform_indexing: true,
-        general_attribute_matrix_vector_indexing: true,
-        general_varying_indexing: true,
-        general_sampler_indexing: true,
-        general_variable_indexing: true,
-        general_constant_matrix_vector_indexing: true,
-    },
-};
+impl ShaderType {
+    pub(crate) fn stage(self) -> glslang_stage_t {
+        match self {
+            ShaderType::Vertex => glslang_stage_t_GLSLANG_STAGE_VERTEX,
+            ShaderType::Fragment => glslang_stage_t_GLSLANG_STAGE_FRAGMENT,
+            ShaderType::Geometry => glslang_stage_t_GLSLANG_STAGE_GEOMETRY,
+            ShaderType::TessellationControl => glslang_stage_t_GLSLANG_STAGE_TESSCONTROL,
+            ShaderType::TessellationEvaluation => glslang_stage_t_GLSLANG_STAGE_TESSEVALUATION,
+            ShaderType::Compute => glslang_stage_t_GLSLANG_STAGE_COMPUTE,
+            ShaderType::Task => glslang_stage_t_GLSLANG_STAGE_TASK_NV,
+            ShaderType::Mesh => glslang_stage_t_GLSLANG_STAGE_MESH_NV,
+            ShaderType::RayGeneration => glslang_stage_t_GLSLANG_STAGE_RAYGEN_NV,
+            ShaderType::Intersection => glslang_stage_t_GLSLANG_STAGE_INTERSECT_NV,
+            ShaderType::AnyHit => glslang_stage_t_GLSLANG_STAGE_ANYHIT_NV,
+            ShaderType::ClosestHit => glslang_stage_t_GLSLANG_STAGE_CLOSESTHIT_NV,
+            ShaderType::Miss => glslang_stage_t_GLSLANG_STAGE_MISS_NV,
+            ShaderType::Callable => glslang_stage_t_GLSLANG_STAGE_CALLABLE_NV,
+        }
+    }
+
+    pub(crate) fn mask(self) -> glslang_stage_mask_t {
+        match self {
+            ShaderType::Vertex => glslang_stage_mask_t_GLSLANG_STAGE_VERTEX_MASK,
+            ShaderType::Fragment => glslang_stage_mask_t_GLSLANG_STAGE_FRAGMENT_MASK,
+            ShaderType::Geometry => glslang_stage_mask_t_GLSLANG_STAGE_GEOMETRY_MASK,
+            ShaderType::TessellationControl => glslang_stage_mask_t_GLSLANG_STAGE_TESSCONTROL_MASK,
+            ShaderType::TessellationEvaluation => {
+                glslang_stage_mask_t_GLSLANG_STAGE_TESSEVALUATION_MASK
+            }
+            ShaderType::Compute => glslang_stage_mask_t_GLSLANG_STAGE_COMPUTE_MASK,
+            ShaderType::Task => glslang_stage_mask_t_GLSLANG_STAGE_TASK_NV_MASK,
+            ShaderType::Mesh => glslang_stage_mask_t_GLSLANG_STAGE_MESH_NV_MASK,
+            ShaderType::RayGeneration => glslang_stage_mask_t_GLSLANG_STAGE_RAYGEN_NV_MASK,
+            ShaderType::Intersection => glslang_stage_mask_t_GLSLANG_STAGE_INTERSECT_NV_MASK,
+            ShaderType::AnyHit => glslang_stage_mask_t_GLSLANG_STAGE_ANYHIT_NV_MASK,
+            ShaderType::ClosestHit => glslang_stage_mask_t_GLSLANG_STAGE_CLOSESTHIT_NV_MASK,
+            ShaderType::Miss => glslang_stage_mask_t_GLSLANG_STAGE_MISS_NV_MASK,
+            ShaderType::Callable => glslang_stage_mask_t_GLSLANG_STAGE_CALLABLE_NV_MASK,
+        }
+    }
+}
+
+/// A set of shader stages, using the same bits as glslang's stage masks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ShaderStages(u32);
+
+impl ShaderStages {
+    pub const VERTEX_PIPELINE: ShaderStages = ShaderStages(
+        (glslang_stage_mask_t_GLSLANG_STAGE_VERTEX_MASK
+            | glslang_stage_mask_t_GLSLANG_STAGE_TESSCONTROL_MASK
+            | glslang_stage_mask_t_GLSLANG_STAGE_TESSEVALUATION_MASK
+            | glslang_stage_mask_t_GLSLANG_STAGE_GEOMETRY_MASK
+            | glslang_stage_mask_t_GLSLANG_STAGE_FRAGMENT_MASK) as u32,
+    );
+    pub const MESH_PIPELINE: ShaderStages = ShaderStages(
+        (glslang_stage_mask_t_GLSLANG_STAGE_TASK_NV_MASK
+            | glslang_stage_mask_t_GLSLANG_STAGE_MESH_NV_MASK
+            | glslang_stage_mask_t_GLSLANG_STAGE_FRAGMENT_MASK) as u32,
+    );
+    pub const RAY_TRACING: ShaderStages = ShaderStages(
+        (glslang_stage_mask_t_GLSLANG_STAGE_RAYGEN_NV_MASK
+            | glslang_stage_mask_t_GLSLANG_STAGE_INTERSECT_NV_MASK
+            | glslang_stage_mask_t_GLSLANG_STAGE_ANYHIT_NV_MASK
+            | glslang_stage_mask_t_GLSLANG_STAGE_CLOSESTHIT_NV_MASK
+            | glslang_stage_mask_t_GLSLANG_STAGE_MISS_NV_MASK
+            | glslang_stage_mask_t_GLSLANG_STAGE_CALLABLE_NV_MASK) as u32,
+    );
+
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        ShaderStages(bits)
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains<S: Into<ShaderStages>>(self, stages: S) -> bool {
+        let stages = stages.into();
+        self.0 & stages.0 == stages.0
+    }
+
+    pub fn insert<S: Into<ShaderStages>>(&mut self, stages: S) {
+        self.0 |= stages.into().0;
+    }
+}
+
+impl From<ShaderType> for ShaderStages {
+    fn from(ty: ShaderType) -> Self {
+        ShaderStages(ty.mask() as u32)
+    }
+}
+
+impl<S: Into<ShaderStages>> std::ops::BitOr<S> for ShaderStages {
+    type Output = ShaderStages;
+
+    fn bitor(mut self, stages: S) -> ShaderStages {
+        self.insert(stages);
+        self
+    }
+}
+
+impl<S: Into<ShaderStages>> std::ops::BitOr<S> for ShaderType {
+    type Output = ShaderStages;
+
+    fn bitor(self, stages: S) -> ShaderStages {
+        ShaderStages::from(self) | stages
+    }
+}
+
+impl std::iter::FromIterator<ShaderType> for ShaderStages {
+    fn from_iter<I: IntoIterator<Item = ShaderType>>(iter: I) -> Self {
+        let mut stages = ShaderStages::empty();
+        for ty in iter {
+            stages.insert(ty);
+        }
+        stages
+    }
+}
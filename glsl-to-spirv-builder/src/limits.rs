@@ -0,0 +1,467 @@
+//! Resource limits shaders are checked against.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::glslang_c_interface::{glslang_limits_s, glslang_resource_t};
+
+macro_rules! resource_limits {
+    (
+        ints { $($int:ident: $int_name:literal = $int_default:expr,)* }
+        bools { $($bool:ident: $bool_name:literal = $bool_default:expr,)* }
+    ) => {
+        /// Implementation limits shaders are checked against, such as the
+        /// maximum compute work group size.
+        ///
+        /// The defaults are glslang's own default limits. Limits can also be
+        /// read from and written to the `.conf` format of glslangValidator,
+        /// see [`from_conf`](Self::from_conf). Presets such as
+        /// [`opengl_es_3_1`](Self::opengl_es_3_1) hold the minimum limits
+        /// guaranteed by a client API, for checking shaders against the
+        /// weakest devices of a platform.
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub struct ResourceLimits {
+            $(pub $int: i32,)*
+            $(pub $bool: bool,)*
+        }
+
+        impl Default for ResourceLimits {
+            fn default() -> Self {
+                ResourceLimits {
+                    $($int: $int_default,)*
+                    $($bool: $bool_default,)*
+                }
+            }
+        }
+
+        impl ResourceLimits {
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            $(
+                pub fn $int(mut self, $int: i32) -> Self {
+                    self.$int = $int;
+                    self
+                }
+            )*
+            $(
+                pub fn $bool(mut self, $bool: bool) -> Self {
+                    self.$bool = $bool;
+                    self
+                }
+            )*
+
+            /// Formats the limits as a glslangValidator `.conf` file, one
+            /// limit per line.
+            pub fn to_conf(&self) -> String {
+                let mut conf = String::new();
+                $(conf.push_str(&format!("{} {}\n", $int_name, self.$int));)*
+                $(conf.push_str(&format!("{} {}\n", $bool_name, self.$bool as i32));)*
+                conf
+            }
+
+            /// Sets the limit called `name` in `.conf` files, returning
+            /// `false` if there is no such limit.
+            fn set(&mut self, name: &str, value: i32) -> bool {
+                match name {
+                    $($int_name => self.$int = value,)*
+                    $($bool_name => self.$bool = value != 0,)*
+                    _ => return false,
+                }
+                true
+            }
+
+            pub(crate) fn resource(&self) -> glslang_resource_t {
+                glslang_resource_t {
+                    $($int: self.$int,)*
+                    limits: glslang_limits_s {
+                        $($bool: self.$bool,)*
+                    },
+                }
+            }
+        }
+    };
+}
+
+resource_limits! {
+    ints {
+        max_lights: "MaxLights" = 32,
+        max_clip_planes: "MaxClipPlanes" = 6,
+        max_texture_units: "MaxTextureUnits" = 32,
+        max_texture_coords: "MaxTextureCoords" = 32,
+        max_vertex_attribs: "MaxVertexAttribs" = 64,
+        max_vertex_uniform_components: "MaxVertexUniformComponents" = 4096,
+        max_varying_floats: "MaxVaryingFloats" = 64,
+        max_vertex_texture_image_units: "MaxVertexTextureImageUnits" = 32,
+        max_combined_texture_image_units: "MaxCombinedTextureImageUnits" = 80,
+        max_texture_image_units: "MaxTextureImageUnits" = 32,
+        max_fragment_uniform_components: "MaxFragmentUniformComponents" = 4096,
+        max_draw_buffers: "MaxDrawBuffers" = 32,
+        max_vertex_uniform_vectors: "MaxVertexUniformVectors" = 128,
+        max_varying_vectors: "MaxVaryingVectors" = 8,
+        max_fragment_uniform_vectors: "MaxFragmentUniformVectors" = 16,
+        max_vertex_output_vectors: "MaxVertexOutputVectors" = 16,
+        max_fragment_input_vectors: "MaxFragmentInputVectors" = 15,
+        min_program_texel_offset: "MinProgramTexelOffset" = -8,
+        max_program_texel_offset: "MaxProgramTexelOffset" = 7,
+        max_clip_distances: "MaxClipDistances" = 8,
+        max_compute_work_group_count_x: "MaxComputeWorkGroupCountX" = 65535,
+        max_compute_work_group_count_y: "MaxComputeWorkGroupCountY" = 65535,
+        max_compute_work_group_count_z: "MaxComputeWorkGroupCountZ" = 65535,
+        max_compute_work_group_size_x: "MaxComputeWorkGroupSizeX" = 1024,
+        max_compute_work_group_size_y: "MaxComputeWorkGroupSizeY" = 1024,
+        max_compute_work_group_size_z: "MaxComputeWorkGroupSizeZ" = 64,
+        max_compute_uniform_components: "MaxComputeUniformComponents" = 1024,
+        max_compute_texture_image_units: "MaxComputeTextureImageUnits" = 16,
+        max_compute_image_uniforms: "MaxComputeImageUniforms" = 8,
+        max_compute_atomic_counters: "MaxComputeAtomicCounters" = 8,
+        max_compute_atomic_counter_buffers: "MaxComputeAtomicCounterBuffers" = 1,
+        max_varying_components: "MaxVaryingComponents" = 60,
+        max_vertex_output_components: "MaxVertexOutputComponents" = 64,
+        max_geometry_input_components: "MaxGeometryInputComponents" = 64,
+        max_geometry_output_components: "MaxGeometryOutputComponents" = 128,
+        max_fragment_input_components: "MaxFragmentInputComponents" = 128,
+        max_image_units: "MaxImageUnits" = 8,
+        max_combined_image_units_and_fragment_outputs: "MaxCombinedImageUnitsAndFragmentOutputs" = 8,
+        max_combined_shader_output_resources: "MaxCombinedShaderOutputResources" = 8,
+        max_image_samples: "MaxImageSamples" = 0,
+        max_vertex_image_uniforms: "MaxVertexImageUniforms" = 0,
+        max_tess_control_image_uniforms: "MaxTessControlImageUniforms" = 0,
+        max_tess_evaluation_image_uniforms: "MaxTessEvaluationImageUniforms" = 0,
+        max_geometry_image_uniforms: "MaxGeometryImageUniforms" = 0,
+        max_fragment_image_uniforms: "MaxFragmentImageUniforms" = 8,
+        max_combined_image_uniforms: "MaxCombinedImageUniforms" = 8,
+        max_geometry_texture_image_units: "MaxGeometryTextureImageUnits" = 16,
+        max_geometry_output_vertices: "MaxGeometryOutputVertices" = 256,
+        max_geometry_total_output_components: "MaxGeometryTotalOutputComponents" = 1024,
+        max_geometry_uniform_components: "MaxGeometryUniformComponents" = 1024,
+        max_geometry_varying_components: "MaxGeometryVaryingComponents" = 64,
+        max_tess_control_input_components: "MaxTessControlInputComponents" = 128,
+        max_tess_control_output_components: "MaxTessControlOutputComponents" = 128,
+        max_tess_control_texture_image_units: "MaxTessControlTextureImageUnits" = 16,
+        max_tess_control_uniform_components: "MaxTessControlUniformComponents" = 1024,
+        max_tess_control_total_output_components: "MaxTessControlTotalOutputComponents" = 4096,
+        max_tess_evaluation_input_components: "MaxTessEvaluationInputComponents" = 128,
+        max_tess_evaluation_output_components: "MaxTessEvaluationOutputComponents" = 128,
+        max_tess_evaluation_texture_image_units: "MaxTessEvaluationTextureImageUnits" = 16,
+        max_tess_evaluation_uniform_components: "MaxTessEvaluationUniformComponents" = 1024,
+        max_tess_patch_components: "MaxTessPatchComponents" = 120,
+        max_patch_vertices: "MaxPatchVertices" = 32,
+        max_tess_gen_level: "MaxTessGenLevel" = 64,
+        max_viewports: "MaxViewports" = 16,
+        max_vertex_atomic_counters: "MaxVertexAtomicCounters" = 0,
+        max_tess_control_atomic_counters: "MaxTessControlAtomicCounters" = 0,
+        max_tess_evaluation_atomic_counters: "MaxTessEvaluationAtomicCounters" = 0,
+        max_geometry_atomic_counters: "MaxGeometryAtomicCounters" = 0,
+        max_fragment_atomic_counters: "MaxFragmentAtomicCounters" = 8,
+        max_combined_atomic_counters: "MaxCombinedAtomicCounters" = 8,
+        max_atomic_counter_bindings: "MaxAtomicCounterBindings" = 1,
+        max_vertex_atomic_counter_buffers: "MaxVertexAtomicCounterBuffers" = 0,
+        max_tess_control_atomic_counter_buffers: "MaxTessControlAtomicCounterBuffers" = 0,
+        max_tess_evaluation_atomic_counter_buffers: "MaxTessEvaluationAtomicCounterBuffers" = 0,
+        max_geometry_atomic_counter_buffers: "MaxGeometryAtomicCounterBuffers" = 0,
+        max_fragment_atomic_counter_buffers: "MaxFragmentAtomicCounterBuffers" = 1,
+        max_combined_atomic_counter_buffers: "MaxCombinedAtomicCounterBuffers" = 1,
+        max_atomic_counter_buffer_size: "MaxAtomicCounterBufferSize" = 16384,
+        max_transform_feedback_buffers: "MaxTransformFeedbackBuffers" = 4,
+        max_transform_feedback_interleaved_components: "MaxTransformFeedbackInterleavedComponents" = 64,
+        max_cull_distances: "MaxCullDistances" = 8,
+        max_combined_clip_and_cull_distances: "MaxCombinedClipAndCullDistances" = 8,
+        max_samples: "MaxSamples" = 4,
+        max_mesh_output_vertices_nv: "MaxMeshOutputVerticesNV" = 256,
+        max_mesh_output_primitives_nv: "MaxMeshOutputPrimitivesNV" = 512,
+        max_mesh_work_group_size_x_nv: "MaxMeshWorkGroupSizeX_NV" = 32,
+        max_mesh_work_group_size_y_nv: "MaxMeshWorkGroupSizeY_NV" = 1,
+        max_mesh_work_group_size_z_nv: "MaxMeshWorkGroupSizeZ_NV" = 1,
+        max_task_work_group_size_x_nv: "MaxTaskWorkGroupSizeX_NV" = 32,
+        max_task_work_group_size_y_nv: "MaxTaskWorkGroupSizeY_NV" = 1,
+        max_task_work_group_size_z_nv: "MaxTaskWorkGroupSizeZ_NV" = 1,
+        max_mesh_view_count_nv: "MaxMeshViewCountNV" = 4,
+    }
+    bools {
+        non_inductive_for_loops: "nonInductiveForLoops" = true,
+        while_loops: "whileLoops" = true,
+        do_while_loops: "doWhileLoops" = true,
+        general_uniform_indexing: "generalUniformIndexing" = true,
+        general_attribute_matrix_vector_indexing: "generalAttributeMatrixVectorIndexing" = true,
+        general_varying_indexing: "generalVaryingIndexing" = true,
+        general_sampler_indexing: "generalSamplerIndexing" = true,
+        general_variable_indexing: "generalVariableIndexing" = true,
+        general_constant_matrix_vector_indexing: "generalConstantMatrixVectorIndexing" = true,
+    }
+}
+
+impl ResourceLimits {
+    /// Parses a glslangValidator `.conf` file: whitespace separated pairs of
+    /// a limit name, such as `MaxLights`, and an integer value. Booleans are
+    /// written as `0` or `1`. Limits that aren't mentioned keep their default.
+    ///
+    /// Like glslangValidator, limits this version doesn't know are skipped, so
+    /// files written for newer glslang versions still load. Use
+    /// [`from_conf_with_warnings`](Self::from_conf_with_warnings) to find out
+    /// which.
+    pub fn from_conf(conf: &str) -> Result<Self, ParseLimitsError> {
+        Self::from_conf_with_warnings(conf).map(|(limits, _)| limits)
+    }
+
+    /// Like [`from_conf`](Self::from_conf), also returning a warning for each
+    /// unknown limit that was skipped.
+    pub fn from_conf_with_warnings(
+        conf: &str,
+    ) -> Result<(Self, Vec<ParseLimitsError>), ParseLimitsError> {
+        let mut limits = ResourceLimits::default();
+        let mut warnings = Vec::new();
+        let mut tokens = conf
+            .lines()
+            .enumerate()
+            .flat_map(|(line, text)| text.split_whitespace().map(move |token| (line + 1, token)));
+
+        while let Some((line, name)) = tokens.next() {
+            let error = |message: String| ParseLimitsError { line, message };
+            let (line, value) = tokens
+                .next()
+                .ok_or_else(|| error(format!("missing value for '{}'", name)))?;
+            let value = value.parse().map_err(|_| ParseLimitsError {
+                line,
+                message: format!("invalid value '{}' for '{}'", value, name),
+            })?;
+            if !limits.set(name, value) {
+                warnings.push(error(format!("unknown limit '{}'", name)));
+            }
+        }
+        Ok((limits, warnings))
+    }
+}
+
+impl FromStr for ResourceLimits {
+    type Err = ParseLimitsError;
+
+    fn from_str(conf: &str) -> Result<Self, ParseLimitsError> {
+        Self::from_conf(conf)
+    }
+}
+
+impl ResourceLimits {
+    /// The minimum limits every Vulkan 1.0 implementation supports.
+    ///
+    /// Limits are taken from the required limits of the Vulkan 1.0 spec. The
+    /// combined texture and image limits are the per descriptor set limits,
+    /// which puts `max_combined_texture_image_units` above glslang's default.
+    /// Limits without a Vulkan counterpart keep glslang's defaults: uniform
+    /// components, atomic counters and transform feedback buffers, which
+    /// Vulkan GLSL doesn't allow, and the compatibility profile limits such
+    /// as `max_lights`.
+    pub fn vulkan_1_0() -> Self {
+        ResourceLimits {
+            // maxVertexInputAttributes, maxVertexOutputComponents
+            max_vertex_attribs: 16,
+            max_vertex_output_components: 64,
+            max_vertex_output_vectors: 16,
+            // maxFragmentInputComponents
+            max_fragment_input_components: 64,
+            max_fragment_input_vectors: 16,
+            max_varying_components: 64,
+            max_varying_vectors: 16,
+            max_varying_floats: 64,
+            // maxTessellation*
+            max_tess_gen_level: 64,
+            max_patch_vertices: 32,
+            max_tess_control_input_components: 64,
+            max_tess_control_output_components: 64,
+            max_tess_patch_components: 120,
+            max_tess_control_total_output_components: 2048,
+            max_tess_evaluation_input_components: 64,
+            max_tess_evaluation_output_components: 64,
+            // maxGeometry*
+            max_geometry_input_components: 64,
+            max_geometry_output_components: 64,
+            max_geometry_output_vertices: 256,
+            max_geometry_total_output_components: 1024,
+            max_geometry_varying_components: 64,
+            // maxPerStageDescriptorSampledImages
+            max_texture_image_units: 16,
+            max_vertex_texture_image_units: 16,
+            max_tess_control_texture_image_units: 16,
+            max_tess_evaluation_texture_image_units: 16,
+            max_geometry_texture_image_units: 16,
+            max_compute_texture_image_units: 16,
+            // maxDescriptorSetSampledImages
+            max_combined_texture_image_units: 96,
+            // maxPerStageDescriptorStorageImages
+            max_vertex_image_uniforms: 4,
+            max_tess_control_image_uniforms: 4,
+            max_tess_evaluation_image_uniforms: 4,
+            max_geometry_image_uniforms: 4,
+            max_fragment_image_uniforms: 4,
+            max_compute_image_uniforms: 4,
+            // maxDescriptorSetStorageImages
+            max_image_units: 24,
+            max_combined_image_uniforms: 24,
+            // maxColorAttachments, maxFragmentCombinedOutputResources
+            max_draw_buffers: 4,
+            max_combined_shader_output_resources: 4,
+            max_combined_image_units_and_fragment_outputs: 4,
+            // maxComputeWorkGroupCount, maxComputeWorkGroupSize
+            max_compute_work_group_count_x: 65535,
+            max_compute_work_group_count_y: 65535,
+            max_compute_work_group_count_z: 65535,
+            max_compute_work_group_size_x: 128,
+            max_compute_work_group_size_y: 128,
+            max_compute_work_group_size_z: 64,
+            // minTexelOffset, maxTexelOffset
+            min_program_texel_offset: -8,
+            max_program_texel_offset: 7,
+            // maxViewports, maxClipDistances, maxCullDistances,
+            // maxCombinedClipAndCullDistances
+            max_viewports: 1,
+            max_clip_distances: 8,
+            max_cull_distances: 8,
+            max_combined_clip_and_cull_distances: 8,
+            // framebufferColorSampleCounts
+            max_samples: 4,
+            ..ResourceLimits::default()
+        }
+    }
+
+    /// The minimum limits every OpenGL ES 3.1 implementation supports.
+    pub fn opengl_es_3_1() -> Self {
+        ResourceLimits {
+            max_vertex_attribs: 16,
+            max_vertex_uniform_components: 1024,
+            max_vertex_uniform_vectors: 256,
+            max_vertex_output_components: 64,
+            max_vertex_output_vectors: 16,
+            max_vertex_texture_image_units: 16,
+            max_vertex_image_uniforms: 0,
+            max_vertex_atomic_counters: 0,
+            max_vertex_atomic_counter_buffers: 0,
+            max_fragment_uniform_components: 896,
+            max_fragment_uniform_vectors: 224,
+            max_fragment_input_components: 60,
+            max_fragment_input_vectors: 15,
+            max_texture_image_units: 16,
+            max_fragment_image_uniforms: 0,
+            max_fragment_atomic_counters: 0,
+            max_fragment_atomic_counter_buffers: 0,
+            max_combined_texture_image_units: 48,
+            max_varying_components: 60,
+            max_varying_vectors: 15,
+            max_draw_buffers: 4,
+            min_program_texel_offset: -8,
+            max_program_texel_offset: 7,
+            max_compute_work_group_count_x: 65535,
+            max_compute_work_group_count_y: 65535,
+            max_compute_work_group_count_z: 65535,
+            max_compute_work_group_size_x: 128,
+            max_compute_work_group_size_y: 128,
+            max_compute_work_group_size_z: 64,
+            max_compute_uniform_components: 512,
+            max_compute_texture_image_units: 16,
+            max_compute_image_uniforms: 4,
+            max_compute_atomic_counters: 8,
+            max_compute_atomic_counter_buffers: 1,
+            max_image_units: 4,
+            max_combined_image_uniforms: 4,
+            max_combined_shader_output_resources: 4,
+            max_combined_atomic_counters: 8,
+            max_combined_atomic_counter_buffers: 1,
+            max_atomic_counter_bindings: 1,
+            max_atomic_counter_buffer_size: 32,
+            max_samples: 4,
+            ..ResourceLimits::default()
+        }
+    }
+
+    /// The minimum limits every OpenGL 4.5 implementation supports.
+    pub fn opengl_4_5() -> Self {
+        ResourceLimits {
+            max_vertex_attribs: 16,
+            max_vertex_uniform_components: 1024,
+            max_vertex_uniform_vectors: 256,
+            max_vertex_output_components: 64,
+            max_vertex_texture_image_units: 16,
+            max_vertex_image_uniforms: 0,
+            max_vertex_atomic_counters: 0,
+            max_vertex_atomic_counter_buffers: 0,
+            max_tess_gen_level: 64,
+            max_patch_vertices: 32,
+            max_tess_control_uniform_components: 1024,
+            max_tess_control_texture_image_units: 16,
+            max_tess_control_input_components: 128,
+            max_tess_control_output_components: 128,
+            max_tess_patch_components: 120,
+            max_tess_control_total_output_components: 4096,
+            max_tess_evaluation_uniform_components: 1024,
+            max_tess_evaluation_texture_image_units: 16,
+            max_tess_evaluation_input_components: 128,
+            max_tess_evaluation_output_components: 128,
+            max_geometry_uniform_components: 1024,
+            max_geometry_texture_image_units: 16,
+            max_geometry_input_components: 64,
+            max_geometry_output_components: 128,
+            max_geometry_output_vertices: 256,
+            max_geometry_total_output_components: 1024,
+            max_fragment_uniform_components: 1024,
+            max_fragment_uniform_vectors: 256,
+            max_fragment_input_components: 128,
+            max_texture_image_units: 16,
+            max_fragment_atomic_counters: 8,
+            max_fragment_atomic_counter_buffers: 1,
+            max_fragment_image_uniforms: 8,
+            min_program_texel_offset: -8,
+            max_program_texel_offset: 7,
+            max_compute_work_group_count_x: 65535,
+            max_compute_work_group_count_y: 65535,
+            max_compute_work_group_count_z: 65535,
+            max_compute_work_group_size_x: 1024,
+            max_compute_work_group_size_y: 1024,
+            max_compute_work_group_size_z: 64,
+            max_compute_uniform_components: 512,
+            max_compute_texture_image_units: 16,
+            max_compute_atomic_counters: 8,
+            max_compute_atomic_counter_buffers: 1,
+            max_compute_image_uniforms: 8,
+            max_combined_texture_image_units: 80,
+            max_varying_components: 60,
+            max_varying_vectors: 15,
+            max_combined_atomic_counters: 8,
+            max_combined_atomic_counter_buffers: 1,
+            max_atomic_counter_bindings: 1,
+            max_atomic_counter_buffer_size: 32,
+            max_image_units: 8,
+            max_combined_image_units_and_fragment_outputs: 8,
+            max_image_samples: 0,
+            max_combined_image_uniforms: 8,
+            max_combined_shader_output_resources: 8,
+            max_draw_buffers: 8,
+            max_clip_distances: 8,
+            max_cull_distances: 8,
+            max_combined_clip_and_cull_distances: 8,
+            max_viewports: 16,
+            max_transform_feedback_buffers: 4,
+            max_transform_feedback_interleaved_components: 64,
+            max_samples: 4,
+            ..ResourceLimits::default()
+        }
+    }
+}
+
+/// An error, or with
+/// [`from_conf_with_warnings`](ResourceLimits::from_conf_with_warnings) a
+/// warning, in a resource limits `.conf` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLimitsError {
+    /// One based line of the error.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseLimitsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseLimitsError {}
@@ -0,0 +1,447 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::glslang_c_interface::*;
+use crate::{IncludeResolver, ResourceLimits};
+
+/// Client API the generated SPIR-V is meant for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TargetEnv {
+    Vulkan1_0,
+    Vulkan1_1,
+    OpenGl4_5,
+}
+
+/// Version of the generated SPIR-V module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpirvVersion {
+    Spirv1_0,
+    Spirv1_1,
+    Spirv1_2,
+    Spirv1_3,
+    Spirv1_4,
+    Spirv1_5,
+}
+
+/// GLSL profile assumed when the source doesn't declare one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Profile {
+    None,
+    Core,
+    Compatibility,
+    Es,
+}
+
+/// Language of the shader source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceLanguage {
+    Glsl,
+    Hlsl,
+}
+
+/// Class of resources whose bindings are shifted together, see
+/// [`CompileOptions::binding_shift`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BindingClass {
+    /// Pure `sampler`s.
+    Sampler,
+    /// Sampled images, including combined image samplers such as `sampler2D`.
+    Texture,
+    /// Storage images.
+    Image,
+    UniformBuffer,
+    StorageBuffer,
+}
+
+/// Options controlling how a shader is compiled.
+///
+/// The defaults match what [`compile`](crate::compile) has always used:
+/// Vulkan 1.0, SPIR-V 1.0 and GLSL 100 with no profile when the source has no
+/// `#version`.
+///
+/// HLSL sources and the binding and location mapping options need parts of
+/// glslang that are only reachable on x86_64 Linux and macOS. Elsewhere they
+/// fail with [`CompileError::Unsupported`](crate::CompileError::Unsupported).
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    pub(crate) source_language: SourceLanguage,
+    pub(crate) entry_point: String,
+    pub(crate) source_entry_point: Option<String>,
+    pub(crate) hlsl_offsets: bool,
+    pub(crate) hlsl_16bit_types: bool,
+    pub(crate) hlsl_legalization: bool,
+    pub(crate) hlsl_dx9_compatible: bool,
+    pub(crate) target_env: TargetEnv,
+    pub(crate) spirv_version: SpirvVersion,
+    pub(crate) default_version: i32,
+    pub(crate) default_profile: Profile,
+    pub(crate) force_default_version_and_profile: bool,
+    pub(crate) forward_compatible: bool,
+    pub(crate) defines: Vec<(String, Option<String>)>,
+    pub(crate) include_resolver: Option<Resolver>,
+    pub(crate) source_name: String,
+    pub(crate) resource_limits: ResourceLimits,
+    pub(crate) validate: bool,
+    pub(crate) auto_map_bindings: bool,
+    pub(crate) auto_map_locations: bool,
+    pub(crate) binding_shifts: Vec<(BindingClass, u32)>,
+    pub(crate) default_descriptor_set: Option<u32>,
+}
+
+#[derive(Clone)]
+pub(crate) struct Resolver(pub Arc<dyn IncludeResolver>);
+
+impl fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("IncludeResolver")
+    }
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            source_language: SourceLanguage::Glsl,
+            entry_point: String::from("main"),
+            source_entry_point: None,
+            hlsl_offsets: false,
+            hlsl_16bit_types: false,
+            hlsl_legalization: false,
+            hlsl_dx9_compatible: false,
+            target_env: TargetEnv::Vulkan1_0,
+            spirv_version: SpirvVersion::Spirv1_0,
+            default_version: 100,
+            default_profile: Profile::None,
+            force_default_version_and_profile: false,
+            forward_compatible: false,
+            defines: Vec::new(),
+            include_resolver: None,
+            source_name: String::new(),
+            resource_limits: ResourceLimits::default(),
+            validate: false,
+            auto_map_bindings: false,
+            auto_map_locations: false,
+            binding_shifts: Vec::new(),
+            default_descriptor_set: None,
+        }
+    }
+}
+
+impl CompileOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn source_language(mut self, source_language: SourceLanguage) -> Self {
+        self.source_language = source_language;
+        self
+    }
+
+    /// Name of the SPIR-V entry point, `main` by default. Unless a
+    /// [`source_entry_point`](Self::source_entry_point) is set, HLSL shaders
+    /// start at the function of that name.
+    pub fn entry_point<S: Into<String>>(mut self, name: S) -> Self {
+        self.entry_point = name.into();
+        self
+    }
+
+    /// Function the shader starts at, renamed to the
+    /// [`entry_point`](Self::entry_point) in the generated SPIR-V.
+    ///
+    /// For GLSL the name is defined to `main` before preprocessing, so the
+    /// source must not declare a `main` function of its own.
+    pub fn source_entry_point<S: Into<String>>(mut self, name: S) -> Self {
+        self.source_entry_point = Some(name.into());
+        self
+    }
+
+    /// Use HLSL packing rules for buffer member offsets instead of std140
+    /// and std430.
+    pub fn hlsl_offsets(mut self, hlsl_offsets: bool) -> Self {
+        self.hlsl_offsets = hlsl_offsets;
+        self
+    }
+
+    /// Map HLSL `min16float` and friends to real 16 bit types.
+    pub fn hlsl_16bit_types(mut self, hlsl_16bit_types: bool) -> Self {
+        self.hlsl_16bit_types = hlsl_16bit_types;
+        self
+    }
+
+    /// Let glslang's HLSL front end accept code that only becomes valid for
+    /// Vulkan after legalization, such as opaque types in structs or local
+    /// variables.
+    ///
+    /// This only sets glslang's HLSL legalization message flag. No SPIR-V
+    /// legalization passes run, as glslang's optimizer is disabled, so such
+    /// code may produce modules that fail [validation](Self::validate).
+    pub fn hlsl_legalization(mut self, hlsl_legalization: bool) -> Self {
+        self.hlsl_legalization = hlsl_legalization;
+        self
+    }
+
+    /// Accept DirectX 9 style HLSL, such as `sampler` objects without
+    /// separate textures.
+    pub fn hlsl_dx9_compatible(mut self, hlsl_dx9_compatible: bool) -> Self {
+        self.hlsl_dx9_compatible = hlsl_dx9_compatible;
+        self
+    }
+
+    pub fn target_env(mut self, target_env: TargetEnv) -> Self {
+        self.target_env = target_env;
+        self
+    }
+
+    pub fn spirv_version(mut self, spirv_version: SpirvVersion) -> Self {
+        self.spirv_version = spirv_version;
+        self
+    }
+
+    /// GLSL version and profile used when the source has no `#version`.
+    pub fn default_version(mut self, version: i32, profile: Profile) -> Self {
+        self.default_version = version;
+        self.default_profile = profile;
+        self
+    }
+
+    /// Use the default version and profile even if the source declares its own.
+    pub fn force_default_version_and_profile(mut self, force: bool) -> Self {
+        self.force_default_version_and_profile = force;
+        self
+    }
+
+    pub fn forward_compatible(mut self, forward_compatible: bool) -> Self {
+        self.forward_compatible = forward_compatible;
+        self
+    }
+
+    /// Defines the macro `name`, optionally with a value, before the source
+    /// is preprocessed.
+    pub fn define<N: Into<String>>(mut self, name: N, value: Option<&str>) -> Self {
+        self.defines
+            .push((name.into(), value.map(|value| value.to_string())));
+        self
+    }
+
+    /// Adds definitions in `NAME` or `NAME=VALUE` form, as taken by
+    /// [`compile`](crate::compile).
+    pub fn shader_defs(mut self, shader_defs: &[String]) -> Self {
+        for def in shader_defs {
+            let def = def.trim();
+            let separator = def
+                .char_indices()
+                .find(|&(_, c)| c == '=' || c.is_whitespace());
+            let (name, value) = match separator {
+                Some((split, c)) => (&def[..split], Some(def[split + c.len_utf8()..].trim())),
+                None => (def, None),
+            };
+            self = self.define(name, value);
+        }
+        self
+    }
+
+    /// Resolver used for `#include` directives. Without one, includes are
+    /// left for glslang to reject.
+    pub fn include_resolver<R: IncludeResolver + 'static>(mut self, resolver: R) -> Self {
+        self.include_resolver = Some(Resolver(Arc::new(resolver)));
+        self
+    }
+
+    /// Name of the compiled source, passed to the include resolver as the
+    /// requesting file of top level includes.
+    pub fn source_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.source_name = name.into();
+        self
+    }
+
+    /// Limits the shader is checked against, glslang's defaults unless set.
+    pub fn resource_limits(mut self, resource_limits: ResourceLimits) -> Self {
+        self.resource_limits = resource_limits;
+        self
+    }
+
+    /// Runs the SPIR-V validator on the generated modules.
+    /// See [`validate`](crate::validate) for where it is available.
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Assigns bindings to resources that don't declare one, like
+    /// glslangValidator's `--auto-map-bindings`. Each stage is mapped on its
+    /// own, and only resources the shader uses are assigned a binding.
+    pub fn auto_map_bindings(mut self, auto_map_bindings: bool) -> Self {
+        self.auto_map_bindings = auto_map_bindings;
+        self
+    }
+
+    /// Assigns locations to inputs and outputs that don't declare one, like
+    /// glslangValidator's `--auto-map-locations`.
+    pub fn auto_map_locations(mut self, auto_map_locations: bool) -> Self {
+        self.auto_map_locations = auto_map_locations;
+        self
+    }
+
+    /// Adds `shift` to the bindings of every resource of `class`, including
+    /// explicit ones, like glslangValidator's `--shift-*-binding` options.
+    /// Assigned bindings start at `shift`.
+    pub fn binding_shift(mut self, class: BindingClass, shift: u32) -> Self {
+        self.binding_shifts.retain(|(c, _)| *c != class);
+        self.binding_shifts.push((class, shift));
+        self
+    }
+
+    /// Descriptor set of uniforms and buffers that don't declare one, instead
+    /// of set 0, like glslangValidator's `--resource-set-binding` with a
+    /// single set. HLSL resources declaring a `space` keep it. Ignored for
+    /// [`TargetEnv::OpenGl4_5`], which has no descriptor sets.
+    pub fn default_descriptor_set(mut self, set: u32) -> Self {
+        self.default_descriptor_set = Some(set);
+        self
+    }
+
+    /// The [`default_descriptor_set`](Self::default_descriptor_set), unless
+    /// the target has no descriptor sets.
+    pub(crate) fn resource_set_binding(&self) -> Option<u32> {
+        match self.target_env {
+            TargetEnv::Vulkan1_0 | TargetEnv::Vulkan1_1 => self.default_descriptor_set,
+            TargetEnv::OpenGl4_5 => None,
+        }
+    }
+
+    /// Whether glslang has to map bindings, locations or descriptor sets after
+    /// linking.
+    pub(crate) fn maps_io(&self) -> bool {
+        self.auto_map_bindings
+            || self.auto_map_locations
+            || !self.binding_shifts.is_empty()
+            || self.resource_set_binding().is_some()
+    }
+
+    /// Feeds every option that affects the compiled output to `state`. The
+    /// include resolver is left out, as only the files it resolves matter,
+    /// and so is validation, which never changes a successful output.
+    pub(crate) fn hash_output_options<H: Hasher>(&self, state: &mut H) {
+        self.source_language.hash(state);
+        self.entry_point.hash(state);
+        self.source_entry_point.hash(state);
+        self.hlsl_offsets.hash(state);
+        self.hlsl_16bit_types.hash(state);
+        self.hlsl_legalization.hash(state);
+        self.hlsl_dx9_compatible.hash(state);
+        self.target_env.hash(state);
+        self.spirv_version.hash(state);
+        self.default_version.hash(state);
+        self.default_profile.hash(state);
+        self.force_default_version_and_profile.hash(state);
+        self.forward_compatible.hash(state);
+        self.defines.hash(state);
+        self.source_name.hash(state);
+        self.resource_limits.hash(state);
+        self.auto_map_bindings.hash(state);
+        self.auto_map_locations.hash(state);
+        self.binding_shifts.hash(state);
+        self.default_descriptor_set.hash(state);
+    }
+
+    pub(crate) fn messages(&self) -> glslang_messages_t {
+        let mut messages = match self.target_env {
+            TargetEnv::Vulkan1_0 | TargetEnv::Vulkan1_1 => {
+                glslang_messages_t_GLSLANG_MSG_SPV_RULES_BIT
+                    | glslang_messages_t_GLSLANG_MSG_VULKAN_RULES_BIT
+            }
+            TargetEnv::OpenGl4_5 => glslang_messages_t_GLSLANG_MSG_SPV_RULES_BIT,
+        };
+
+        if self.source_language == SourceLanguage::Hlsl {
+            messages |= glslang_messages_t_GLSLANG_MSG_READ_HLSL_BIT;
+            let flags = [
+                (
+                    self.hlsl_offsets,
+                    glslang_messages_t_GLSLANG_MSG_HLSL_OFFSETS_BIT,
+                ),
+                (
+                    self.hlsl_16bit_types,
+                    glslang_messages_t_GLSLANG_MSG_HLSL_ENABLE_16BIT_TYPES_BIT,
+                ),
+                (
+                    self.hlsl_legalization,
+                    glslang_messages_t_GLSLANG_MSG_HLSL_LEGALIZATION_BIT,
+                ),
+                (
+                    self.hlsl_dx9_compatible,
+                    glslang_messages_t_GLSLANG_MSG_HLSL_DX9_COMPATIBLE_BIT,
+                ),
+            ];
+            for (enabled, flag) in flags.iter() {
+                if *enabled {
+                    messages |= flag;
+                }
+            }
+        }
+        messages
+    }
+}
+
+impl BindingClass {
+    /// The matching `glslang::TResourceType`.
+    pub(crate) fn resource_type(self) -> i32 {
+        match self {
+            BindingClass::Sampler => 0,
+            BindingClass::Texture => 1,
+            BindingClass::Image => 2,
+            BindingClass::UniformBuffer => 3,
+            BindingClass::StorageBuffer => 4,
+        }
+    }
+}
+
+impl SourceLanguage {
+    pub(crate) fn source(self) -> glslang_source_t {
+        match self {
+            SourceLanguage::Glsl => glslang_source_t_GLSLANG_SOURCE_GLSL,
+            SourceLanguage::Hlsl => glslang_source_t_GLSLANG_SOURCE_HLSL,
+        }
+    }
+}
+
+impl TargetEnv {
+    pub(crate) fn client(self) -> glslang_client_t {
+        match self {
+            TargetEnv::Vulkan1_0 | TargetEnv::Vulkan1_1 => glslang_client_t_GLSLANG_CLIENT_VULKAN,
+            TargetEnv::OpenGl4_5 => glslang_client_t_GLSLANG_CLIENT_OPENGL,
+        }
+    }
+
+    pub(crate) fn client_version(self) -> glslang_target_client_version_t {
+        match self {
+            TargetEnv::Vulkan1_0 => glslang_target_client_version_t_GLSLANG_TARGET_VULKAN_1_0,
+            TargetEnv::Vulkan1_1 => glslang_target_client_version_t_GLSLANG_TARGET_VULKAN_1_1,
+            TargetEnv::OpenGl4_5 => glslang_target_client_version_t_GLSLANG_TARGET_OPENGL_450,
+        }
+    }
+}
+
+impl SpirvVersion {
+    pub(crate) fn target_language_version(self) -> glslang_target_language_version_t {
+        match self {
+            SpirvVersion::Spirv1_0 => glslang_target_language_version_t_GLSLANG_TARGET_SPV_1_0,
+            SpirvVersion::Spirv1_1 => glslang_target_language_version_t_GLSLANG_TARGET_SPV_1_1,
+            SpirvVersion::Spirv1_2 => glslang_target_language_version_t_GLSLANG_TARGET_SPV_1_2,
+            SpirvVersion::Spirv1_3 => glslang_target_language_version_t_GLSLANG_TARGET_SPV_1_3,
+            SpirvVersion::Spirv1_4 => glslang_target_language_version_t_GLSLANG_TARGET_SPV_1_4,
+            SpirvVersion::Spirv1_5 => glslang_target_language_version_t_GLSLANG_TARGET_SPV_1_5,
+        }
+    }
+}
+
+impl Profile {
+    pub(crate) fn profile(self) -> glslang_profile_t {
+        match self {
+            Profile::None => glslang_profile_t_GLSLANG_NO_PROFILE,
+            Profile::Core => glslang_profile_t_GLSLANG_CORE_PROFILE,
+            Profile::Compatibility => glslang_profile_t_GLSLANG_COMPATIBILITY_PROFILE,
+            Profile::Es => glslang_profile_t_GLSLANG_ES_PROFILE,
+        }
+    }
+}
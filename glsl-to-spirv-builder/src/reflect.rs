@@ -0,0 +1,777 @@
+//! Reflection of the resources and interface of SPIR-V modules.
+//!
+//! glslang's own reflection lives on its C++ `TProgram`, which the C interface
+//! doesn't expose, so the SPIR-V is read instead. This works the same for
+//! modules compiled here and for precompiled ones. Names come from debug
+//! instructions and are empty once those have been stripped.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::spirv::{self, decoration, op, storage_class, Instruction, Types};
+use crate::ShaderType;
+
+/// Resources and interface variables of a module.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Reflection {
+    /// Uniform blocks, ordered by set and binding.
+    pub uniform_blocks: Vec<Block>,
+    /// Shader storage blocks, ordered by set and binding.
+    pub storage_buffers: Vec<Block>,
+    /// Samplers, images, texel buffers and acceleration structures, ordered
+    /// by set and binding.
+    pub resources: Vec<Resource>,
+    /// User-defined inputs, ordered by location. Members of input blocks are
+    /// listed on their own.
+    pub inputs: Vec<InterfaceVariable>,
+    /// User-defined outputs, ordered by location. Members of output blocks
+    /// are listed on their own.
+    pub outputs: Vec<InterfaceVariable>,
+    /// The push constant block, if the module uses one.
+    pub push_constants: Option<PushConstantRange>,
+    /// Specialization constants, ordered by constant id. Only the first
+    /// constant declared with a given id is listed.
+    pub specialization_constants: Vec<SpecializationConstant>,
+    /// Entry points, in the order the module declares them.
+    pub entry_points: Vec<EntryPoint>,
+}
+
+impl Reflection {
+    /// Every descriptor the module uses, ordered by set and binding.
+    pub fn descriptor_bindings(&self) -> Vec<DescriptorBinding> {
+        let blocks = |blocks: &[Block], kind| {
+            blocks
+                .iter()
+                .map(move |block| DescriptorBinding {
+                    set: block.set,
+                    binding: block.binding,
+                    count: block.count,
+                    kind,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut bindings = blocks(&self.uniform_blocks, DescriptorKind::UniformBuffer);
+        bindings.extend(blocks(&self.storage_buffers, DescriptorKind::StorageBuffer));
+        bindings.extend(self.resources.iter().map(|resource| DescriptorBinding {
+            set: resource.set,
+            binding: resource.binding,
+            count: resource.count,
+            kind: DescriptorKind::Resource(resource.kind),
+        }));
+        bindings.sort_by_key(|b| (b.set, b.binding));
+        bindings
+    }
+
+    /// The descriptor set numbers the module uses, in ascending order.
+    pub fn descriptor_sets(&self) -> Vec<u32> {
+        let mut sets: Vec<u32> = self.descriptor_bindings().iter().map(|b| b.set).collect();
+        sets.dedup();
+        sets
+    }
+}
+
+/// A uniform or storage block.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Block {
+    /// Name of the block type, e.g. `Camera` for `uniform Camera { .. } camera`.
+    pub name: String,
+    pub set: u32,
+    pub binding: u32,
+    /// Number of array elements, or 0 for a runtime-sized array of blocks.
+    pub count: u32,
+    /// Size in bytes, excluding any runtime-sized array at the end.
+    pub size: u32,
+    pub members: Vec<BlockMember>,
+}
+
+/// A member of a [`Block`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockMember {
+    pub name: String,
+    /// Offset in bytes from the start of the block.
+    pub offset: u32,
+    /// Size in bytes, 0 for a runtime-sized array.
+    pub size: u32,
+    /// GLSL name of the type, e.g. `mat4` or `vec4[]`.
+    pub ty: String,
+}
+
+/// An opaque resource bound to a descriptor.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Resource {
+    pub name: String,
+    pub set: u32,
+    pub binding: u32,
+    /// Number of array elements, or 0 for a runtime-sized array.
+    pub count: u32,
+    pub kind: ResourceKind,
+}
+
+/// What an opaque [`Resource`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    /// A `sampler`.
+    Sampler,
+    /// A `texture2D` and the like, sampled through a separate sampler.
+    SampledImage,
+    /// A `sampler2D` and the like.
+    CombinedImageSampler,
+    /// An `image2D` and the like.
+    StorageImage,
+    /// A `textureBuffer`.
+    UniformTexelBuffer,
+    /// An `imageBuffer`.
+    StorageTexelBuffer,
+    /// An `accelerationStructureEXT`.
+    AccelerationStructure,
+}
+
+/// A descriptor of any kind, as needed for a descriptor set layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DescriptorBinding {
+    pub set: u32,
+    pub binding: u32,
+    /// Number of array elements, or 0 for a runtime-sized array.
+    pub count: u32,
+    pub kind: DescriptorKind,
+}
+
+/// What a [`DescriptorBinding`] is bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DescriptorKind {
+    UniformBuffer,
+    StorageBuffer,
+    Resource(ResourceKind),
+}
+
+/// The push constant block of a module.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PushConstantRange {
+    /// Name of the block type.
+    pub name: String,
+    /// Offset in bytes of the first member.
+    pub offset: u32,
+    /// Size in bytes from `offset` to the end of the last member.
+    pub size: u32,
+    pub members: Vec<BlockMember>,
+}
+
+/// A scalar constant that can be overridden when creating a pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpecializationConstant {
+    /// The `constant_id` of the constant.
+    pub id: u32,
+    pub name: String,
+    /// GLSL name of the type, e.g. `uint`.
+    pub ty: String,
+    /// Bits of the default value, 0 or 1 for a `bool`.
+    pub default: u64,
+}
+
+/// An entry point of a module.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntryPoint {
+    pub name: String,
+    /// The stage, or `None` for execution models without a [`ShaderType`],
+    /// such as OpenCL kernels.
+    pub stage: Option<ShaderType>,
+    /// Local workgroup size of compute, task and mesh shaders. Sizes given by
+    /// specialization constants are their default values.
+    pub workgroup_size: Option<[u32; 3]>,
+}
+
+/// A word stream that isn't a readable SPIR-V module.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReflectError {
+    /// Index of the malformed instruction, if the header was readable.
+    pub instruction: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.instruction {
+            Some(instruction) => write!(f, "instruction {}: {}", instruction, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl Error for ReflectError {}
+
+/// A user-defined input or output.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InterfaceVariable {
+    /// Name of the variable, or `Block.member` for a member of a block.
+    pub name: String,
+    pub location: u32,
+    pub component: u32,
+    /// GLSL name of the type, e.g. `vec3`.
+    pub ty: String,
+}
+
+/// Reflects a SPIR-V module, such as the `spirv` of a [`CompileOutput`] or a
+/// precompiled `.spv` file.
+///
+/// The words must be in native byte order. Only the structure of the module
+/// is checked, so instructions that can't be interpreted are left out rather
+/// than reported.
+///
+/// [`CompileOutput`]: crate::CompileOutput
+pub fn reflect(words: &[u32]) -> Result<Reflection, ReflectError> {
+    check(words)?;
+    let module = Module::new(words);
+    let mut reflection = Reflection::default();
+
+    for &(pointer, id, class) in &module.variables {
+        let ty = match module.types.pointee(pointer) {
+            Some(ty) => ty,
+            None => continue,
+        };
+        match class {
+            storage_class::UNIFORM | storage_class::STORAGE_BUFFER => {
+                let (ty, count) = module.unwrap_array(ty);
+                let block = module.block(id, ty, count);
+                if class == storage_class::STORAGE_BUFFER
+                    || module.decoration(ty, decoration::BUFFER_BLOCK).is_some()
+                {
+                    reflection.storage_buffers.push(block);
+                } else {
+                    reflection.uniform_blocks.push(block);
+                }
+            }
+            storage_class::UNIFORM_CONSTANT => {
+                let (ty, count) = module.unwrap_array(ty);
+                if let Some(kind) = module.resource_kind(ty) {
+                    reflection.resources.push(Resource {
+                        name: module.name(id),
+                        set: module
+                            .decoration(id, decoration::DESCRIPTOR_SET)
+                            .unwrap_or(0),
+                        binding: module.decoration(id, decoration::BINDING).unwrap_or(0),
+                        count,
+                        kind,
+                    });
+                }
+            }
+            storage_class::INPUT => reflection.inputs.extend(module.interface(id, ty)),
+            storage_class::OUTPUT => reflection.outputs.extend(module.interface(id, ty)),
+            storage_class::PUSH_CONSTANT => {
+                let block = module.block(id, ty, 1);
+                let offset = block.members.iter().map(|m| m.offset).min().unwrap_or(0);
+                reflection.push_constants = Some(PushConstantRange {
+                    name: block.name,
+                    offset,
+                    size: block.size - offset,
+                    members: block.members,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    for &(ty, id, default) in &module.specialization_constants {
+        if let Some(spec_id) = module.decoration(id, decoration::SPEC_ID) {
+            reflection
+                .specialization_constants
+                .push(SpecializationConstant {
+                    id: spec_id,
+                    name: module.name(id),
+                    ty: module.types.describe(ty),
+                    default,
+                });
+        }
+    }
+
+    for &(model, id, ref name) in &module.entry_points {
+        reflection.entry_points.push(EntryPoint {
+            name: name.clone(),
+            stage: stage(model),
+            workgroup_size: module.workgroup_size(id),
+        });
+    }
+
+    reflection
+        .uniform_blocks
+        .sort_by_key(|b| (b.set, b.binding));
+    reflection
+        .storage_buffers
+        .sort_by_key(|b| (b.set, b.binding));
+    reflection.resources.sort_by_key(|r| (r.set, r.binding));
+    reflection.inputs.sort_by_key(|v| (v.location, v.component));
+    reflection
+        .outputs
+        .sort_by_key(|v| (v.location, v.component));
+    // glslang declares a second constant with the same id for sizes given
+    // with `local_size_x_id` and the like. The first one is kept.
+    reflection.specialization_constants.sort_by_key(|c| c.id);
+    reflection.specialization_constants.dedup_by_key(|c| c.id);
+    Ok(reflection)
+}
+
+/// Checks the header and that every instruction fits in the module.
+fn check(words: &[u32]) -> Result<(), ReflectError> {
+    let header = |message: &str| ReflectError {
+        instruction: None,
+        message: message.to_string(),
+    };
+    match words.first() {
+        _ if words.len() < spirv::HEADER_LEN => Err(header("module is shorter than its header")),
+        Some(&spirv::MAGIC) => Ok(()),
+        Some(magic) if magic.swap_bytes() == spirv::MAGIC => {
+            Err(header("module is not in native byte order"))
+        }
+        _ => Err(header("module doesn't start with the SPIR-V magic number")),
+    }?;
+
+    let mut rest = &words[spirv::HEADER_LEN..];
+    let mut instruction = 0;
+    while let Some(first) = rest.first() {
+        let count = (first >> 16) as usize;
+        if count == 0 || count > rest.len() {
+            return Err(ReflectError {
+                instruction: Some(instruction),
+                message: format!("invalid word count {}", count),
+            });
+        }
+        rest = &rest[count..];
+        instruction += 1;
+    }
+    Ok(())
+}
+
+/// The stage of a SPIR-V execution model.
+fn stage(model: u32) -> Option<ShaderType> {
+    Some(match model {
+        0 => ShaderType::Vertex,
+        1 => ShaderType::TessellationControl,
+        2 => ShaderType::TessellationEvaluation,
+        3 => ShaderType::Geometry,
+        4 => ShaderType::Fragment,
+        5 => ShaderType::Compute,
+        5267 | 5364 => ShaderType::Task,
+        5268 | 5365 => ShaderType::Mesh,
+        5313 => ShaderType::RayGeneration,
+        5314 => ShaderType::Intersection,
+        5315 => ShaderType::AnyHit,
+        5316 => ShaderType::ClosestHit,
+        5317 => ShaderType::Miss,
+        5318 => ShaderType::Callable,
+        _ => return None,
+    })
+}
+
+/// The parts of a module reflection needs, indexed by id.
+struct Module<'a> {
+    types: Types<'a>,
+    names: HashMap<u32, String>,
+    member_names: HashMap<(u32, u32), String>,
+    decorations: HashMap<(u32, u32), u32>,
+    member_decorations: HashMap<(u32, u32, u32), u32>,
+    /// Pointer type, result id and storage class of every global variable.
+    variables: Vec<(u32, u32, u32)>,
+    /// Type, result id and default value of every scalar specialization
+    /// constant.
+    specialization_constants: Vec<(u32, u32, u64)>,
+    /// Constituents of composite constants.
+    composites: HashMap<u32, &'a [u32]>,
+    /// Execution model, function id and name of every entry point.
+    entry_points: Vec<(u32, u32, String)>,
+    /// Execution modes with their operands, per entry point function.
+    execution_modes: HashMap<(u32, u32), &'a [u32]>,
+}
+
+impl<'a> Module<'a> {
+    fn new(words: &'a [u32]) -> Self {
+        let mut module = Module {
+            types: Types::default(),
+            names: HashMap::new(),
+            member_names: HashMap::new(),
+            decorations: HashMap::new(),
+            member_decorations: HashMap::new(),
+            variables: Vec::new(),
+            specialization_constants: Vec::new(),
+            composites: HashMap::new(),
+            entry_points: Vec::new(),
+            execution_modes: HashMap::new(),
+        };
+
+        for instruction in spirv::instructions(words) {
+            module.types.insert(instruction);
+            match instruction {
+                Instruction {
+                    opcode: op::FUNCTION,
+                    ..
+                } => break,
+                Instruction {
+                    opcode: op::NAME,
+                    operands: [id, name @ ..],
+                } => {
+                    module.names.insert(*id, spirv::string(name).0);
+                }
+                Instruction {
+                    opcode: op::MEMBER_NAME,
+                    operands: [id, member, name @ ..],
+                } => {
+                    module
+                        .member_names
+                        .insert((*id, *member), spirv::string(name).0);
+                }
+                // Decorations without a literal, such as `Block`, are recorded
+                // as 0.
+                Instruction {
+                    opcode: op::DECORATE,
+                    operands: [id, decoration, value @ ..],
+                } => {
+                    let value = value.first().copied().unwrap_or(0);
+                    module.decorations.insert((*id, *decoration), value);
+                }
+                Instruction {
+                    opcode: op::MEMBER_DECORATE,
+                    operands: [id, member, decoration, value @ ..],
+                } => {
+                    let value = value.first().copied().unwrap_or(0);
+                    module
+                        .member_decorations
+                        .insert((*id, *member, *decoration), value);
+                }
+                Instruction {
+                    opcode: op::VARIABLE,
+                    operands: [pointer, id, class, ..],
+                } => module.variables.push((*pointer, *id, *class)),
+                Instruction {
+                    opcode: op::SPEC_CONSTANT_TRUE,
+                    operands: [ty, id, ..],
+                } => module.specialization_constants.push((*ty, *id, 1)),
+                Instruction {
+                    opcode: op::SPEC_CONSTANT_FALSE,
+                    operands: [ty, id, ..],
+                } => module.specialization_constants.push((*ty, *id, 0)),
+                Instruction {
+                    opcode: op::SPEC_CONSTANT,
+                    operands: [ty, id, value @ ..],
+                } => {
+                    let low = u64::from(value.first().copied().unwrap_or(0));
+                    let high = u64::from(value.get(1).copied().unwrap_or(0));
+                    module
+                        .specialization_constants
+                        .push((*ty, *id, high << 32 | low));
+                }
+                Instruction {
+                    opcode: op::CONSTANT_COMPOSITE,
+                    operands: [_, id, constituents @ ..],
+                }
+                | Instruction {
+                    opcode: op::SPEC_CONSTANT_COMPOSITE,
+                    operands: [_, id, constituents @ ..],
+                } => {
+                    module.composites.insert(*id, constituents);
+                }
+                Instruction {
+                    opcode: op::ENTRY_POINT,
+                    operands: [model, function, name @ ..],
+                } => {
+                    let name = spirv::string(name).0;
+                    module.entry_points.push((*model, *function, name));
+                }
+                Instruction {
+                    opcode: op::EXECUTION_MODE,
+                    operands: [function, mode, operands @ ..],
+                } => {
+                    module.execution_modes.insert((*function, *mode), operands);
+                }
+                _ => {}
+            }
+        }
+        module
+    }
+
+    /// Value of a scalar constant or specialization constant.
+    fn constant(&self, id: u32) -> Option<u32> {
+        self.types.constant(id).or_else(|| {
+            self.specialization_constants
+                .iter()
+                .find(|c| c.1 == id)
+                .map(|c| c.2 as u32)
+        })
+    }
+
+    /// The local workgroup size of an entry point function. A constant
+    /// decorated as the `WorkgroupSize` built-in overrides the execution mode.
+    fn workgroup_size(&self, function: u32) -> Option<[u32; 3]> {
+        let built_in = self.composites.iter().find(|(id, _)| {
+            self.decoration(**id, decoration::BUILT_IN) == Some(BUILT_IN_WORKGROUP_SIZE)
+        });
+        let (sizes, ids) = match built_in {
+            Some((_, constituents)) => (*constituents, true),
+            None => match self.execution_modes.get(&(function, MODE_LOCAL_SIZE)) {
+                Some(literals) => (*literals, false),
+                None => (
+                    *self.execution_modes.get(&(function, MODE_LOCAL_SIZE_ID))?,
+                    true,
+                ),
+            },
+        };
+
+        let mut size = [1; 3];
+        for (size, &value) in size.iter_mut().zip(sizes) {
+            *size = if ids { self.constant(value)? } else { value };
+        }
+        Some(size)
+    }
+
+    fn name(&self, id: u32) -> String {
+        self.names.get(&id).cloned().unwrap_or_default()
+    }
+
+    fn decoration(&self, id: u32, decoration: u32) -> Option<u32> {
+        self.decorations.get(&(id, decoration)).copied()
+    }
+
+    fn member_decoration(&self, id: u32, member: u32, decoration: u32) -> Option<u32> {
+        self.member_decorations
+            .get(&(id, member, decoration))
+            .copied()
+    }
+
+    /// Members of a struct type.
+    fn members(&self, ty: u32) -> &'a [u32] {
+        match self.types.get(ty) {
+            Some(Instruction {
+                opcode: op::TYPE_STRUCT,
+                operands: [_, members @ ..],
+            }) => members,
+            _ => &[],
+        }
+    }
+
+    /// The element type and length of an array type, or `ty` itself and 1.
+    /// Runtime-sized arrays have a length of 0.
+    fn unwrap_array(&self, ty: u32) -> (u32, u32) {
+        match self.types.get(ty) {
+            Some(Instruction {
+                opcode: op::TYPE_ARRAY,
+                operands: [_, element, length, ..],
+            }) => (*element, self.types.constant(*length).unwrap_or(0)),
+            Some(Instruction {
+                opcode: op::TYPE_RUNTIME_ARRAY,
+                operands: [_, element, ..],
+            }) => (*element, 0),
+            _ => (ty, 1),
+        }
+    }
+
+    fn block(&self, variable: u32, ty: u32, count: u32) -> Block {
+        let members: Vec<BlockMember> = self
+            .members(ty)
+            .iter()
+            .enumerate()
+            .map(|(i, &member_ty)| {
+                let i = i as u32;
+                let matrix_stride = self.member_decoration(ty, i, decoration::MATRIX_STRIDE);
+                let row_major = self
+                    .member_decoration(ty, i, decoration::ROW_MAJOR)
+                    .is_some();
+                BlockMember {
+                    name: self.member_names.get(&(ty, i)).cloned().unwrap_or_default(),
+                    offset: self
+                        .member_decoration(ty, i, decoration::OFFSET)
+                        .unwrap_or(0),
+                    size: self.size(member_ty, matrix_stride, row_major),
+                    ty: self.types.describe(member_ty),
+                }
+            })
+            .collect();
+
+        Block {
+            name: self.name(ty),
+            set: self
+                .decoration(variable, decoration::DESCRIPTOR_SET)
+                .unwrap_or(0),
+            binding: self.decoration(variable, decoration::BINDING).unwrap_or(0),
+            count,
+            size: members.iter().map(|m| m.offset + m.size).max().unwrap_or(0),
+            members,
+        }
+    }
+
+    /// Size in bytes of a type inside a block. `matrix_stride` and
+    /// `row_major` come from the member the type belongs to.
+    fn size(&self, ty: u32, matrix_stride: Option<u32>, row_major: bool) -> u32 {
+        let instruction = match self.types.get(ty) {
+            Some(instruction) => instruction,
+            None => return 0,
+        };
+
+        match (instruction.opcode, instruction.operands) {
+            (op::TYPE_BOOL, _) => 4,
+            (op::TYPE_INT, [_, width, ..]) | (op::TYPE_FLOAT, [_, width, ..]) => width / 8,
+            (op::TYPE_VECTOR, [_, component, count, ..]) => {
+                count * self.size(*component, None, false)
+            }
+            (op::TYPE_MATRIX, [_, column, columns, ..]) => {
+                let rows = match self.types.get(*column) {
+                    Some(Instruction {
+                        operands: [_, _, rows, ..],
+                        ..
+                    }) => *rows,
+                    _ => 0,
+                };
+                match matrix_stride {
+                    Some(stride) if row_major => rows * stride,
+                    Some(stride) => columns * stride,
+                    None => columns * self.size(*column, None, false),
+                }
+            }
+            (op::TYPE_ARRAY, [_, element, length, ..]) => {
+                let length = self.types.constant(*length).unwrap_or(0);
+                let stride = self
+                    .decoration(ty, decoration::ARRAY_STRIDE)
+                    .unwrap_or_else(|| self.size(*element, matrix_stride, row_major));
+                length * stride
+            }
+            (op::TYPE_STRUCT, [_, members @ ..]) => members
+                .iter()
+                .enumerate()
+                .map(|(i, &member_ty)| {
+                    let i = i as u32;
+                    let offset = self
+                        .member_decoration(ty, i, decoration::OFFSET)
+                        .unwrap_or(0);
+                    let matrix_stride = self.member_decoration(ty, i, decoration::MATRIX_STRIDE);
+                    let row_major = self
+                        .member_decoration(ty, i, decoration::ROW_MAJOR)
+                        .is_some();
+                    offset + self.size(member_ty, matrix_stride, row_major)
+                })
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn resource_kind(&self, ty: u32) -> Option<ResourceKind> {
+        match self.types.get(ty)? {
+            Instruction {
+                opcode: op::TYPE_SAMPLER,
+                ..
+            } => Some(ResourceKind::Sampler),
+            Instruction {
+                opcode: op::TYPE_SAMPLED_IMAGE,
+                ..
+            } => Some(ResourceKind::CombinedImageSampler),
+            Instruction {
+                opcode: op::TYPE_ACCELERATION_STRUCTURE,
+                ..
+            } => Some(ResourceKind::AccelerationStructure),
+            Instruction {
+                opcode: op::TYPE_IMAGE,
+                operands: [_, _, dim, _, _, _, sampled, ..],
+            } => Some(match (*dim == DIM_BUFFER, *sampled == SAMPLED_STORAGE) {
+                (false, false) => ResourceKind::SampledImage,
+                (false, true) => ResourceKind::StorageImage,
+                (true, false) => ResourceKind::UniformTexelBuffer,
+                (true, true) => ResourceKind::StorageTexelBuffer,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The user-defined interface variables declared by `variable`, which has
+    /// type `ty`. Blocks are split into their members, and built-ins and
+    /// variables without a location are left out.
+    fn interface(&self, variable: u32, ty: u32) -> Vec<InterfaceVariable> {
+        if self.decoration(variable, decoration::BUILT_IN).is_some() {
+            return Vec::new();
+        }
+        let location = self.decoration(variable, decoration::LOCATION);
+        let component = self
+            .decoration(variable, decoration::COMPONENT)
+            .unwrap_or(0);
+
+        // Blocks may be arrayed per vertex.
+        let (block, _) = self.unwrap_array(ty);
+        if self.decoration(block, decoration::BLOCK).is_none() {
+            return match location {
+                Some(location) => vec![InterfaceVariable {
+                    name: self.name(variable),
+                    location,
+                    component,
+                    ty: self.types.describe(ty),
+                }],
+                None => Vec::new(),
+            };
+        }
+
+        let block_name = self.name(block);
+        let mut next = location;
+        let mut variables = Vec::new();
+        for (i, &member_ty) in self.members(block).iter().enumerate() {
+            let i = i as u32;
+            if self
+                .member_decoration(block, i, decoration::BUILT_IN)
+                .is_some()
+            {
+                continue;
+            }
+            let location = match self.member_decoration(block, i, decoration::LOCATION) {
+                Some(location) => location,
+                None => match next {
+                    Some(location) => location,
+                    None => continue,
+                },
+            };
+            next = Some(location + self.locations(member_ty));
+
+            let member = self
+                .member_names
+                .get(&(block, i))
+                .cloned()
+                .unwrap_or_default();
+            variables.push(InterfaceVariable {
+                name: format!("{}.{}", block_name, member),
+                location,
+                component: self
+                    .member_decoration(block, i, decoration::COMPONENT)
+                    .unwrap_or(0),
+                ty: self.types.describe(member_ty),
+            });
+        }
+        variables
+    }
+
+    /// Number of locations a type occupies.
+    fn locations(&self, ty: u32) -> u32 {
+        let instruction = match self.types.get(ty) {
+            Some(instruction) => instruction,
+            None => return 1,
+        };
+
+        match (instruction.opcode, instruction.operands) {
+            // dvec3 and dvec4 take two locations.
+            (op::TYPE_VECTOR, [_, component, count, ..])
+                if *count > 2 && self.size(*component, None, false) == 8 =>
+            {
+                2
+            }
+            (op::TYPE_MATRIX, [_, column, columns, ..]) => columns * self.locations(*column),
+            (op::TYPE_ARRAY, [_, element, length, ..]) => {
+                self.types.constant(*length).unwrap_or(1) * self.locations(*element)
+            }
+            (op::TYPE_STRUCT, [_, members @ ..]) => {
+                members.iter().map(|m| self.locations(*m)).sum()
+            }
+            _ => 1,
+        }
+    }
+}
+
+// Operands of OpTypeImage.
+const DIM_BUFFER: u32 = 5;
+const SAMPLED_STORAGE: u32 = 2;
+
+const BUILT_IN_WORKGROUP_SIZE: u32 = 25;
+const MODE_LOCAL_SIZE: u32 = 17;
+const MODE_LOCAL_SIZE_ID: u32 = 38;
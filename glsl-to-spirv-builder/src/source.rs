@@ -0,0 +1,314 @@
+//! Helpers for rewriting GLSL source before it is handed to glslang.
+
+use crate::{CompileOptions, Profile};
+
+/// The lines of `source` with comments blanked out, so directives inside
+/// comments are ignored. Line numbering is preserved.
+pub(crate) fn code_lines(source: &str) -> Vec<String> {
+    let mut in_block = false;
+    source
+        .split('\n')
+        .map(|line| {
+            let mut code = String::with_capacity(line.len());
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                if in_block {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        in_block = false;
+                        code.push(' ');
+                    }
+                } else if c == '/' && chars.peek() == Some(&'/') {
+                    break;
+                } else if c == '/' && chars.peek() == Some(&'*') {
+                    chars.next();
+                    in_block = true;
+                } else {
+                    code.push(c);
+                }
+            }
+            code
+        })
+        .collect()
+}
+
+/// If `line` is a preprocessor directive named `name`, returns its arguments.
+pub(crate) fn directive<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let args = rest.strip_prefix(name)?;
+    if args.is_empty() || args.starts_with(char::is_whitespace) {
+        Some(args.trim())
+    } else {
+        None
+    }
+}
+
+/// The `#version` directive of a shader.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Version {
+    /// Zero based index of the line holding the directive.
+    pub line: usize,
+    pub number: i32,
+    pub es: bool,
+}
+
+pub(crate) fn find_version(lines: &[String]) -> Option<Version> {
+    lines.iter().enumerate().find_map(|(line, code)| {
+        let args = directive(code, "version")?;
+        let mut args = args.split_whitespace();
+        let number = args.next()?.parse().ok()?;
+        Some(Version {
+            line,
+            number,
+            // GLSL ES 1.00 doesn't spell out its profile.
+            es: number == 100 || args.next() == Some("es"),
+        })
+    })
+}
+
+/// Formats a `#line` directive that makes the following line `next_line`
+/// (one based) of source string `source`.
+///
+/// Before GLSL 330 and outside of ES, `#line N` refers to the directive's own
+/// line rather than the next one.
+pub(crate) fn line_directive(
+    next_line: usize,
+    source: u32,
+    version: Option<Version>,
+    options: &CompileOptions,
+) -> String {
+    let (number, es) = match version {
+        Some(version) if !options.force_default_version_and_profile => (version.number, version.es),
+        _ => (
+            options.default_version,
+            options.default_profile == Profile::Es || options.default_version == 100,
+        ),
+    };
+
+    let line = if es || number >= 330 {
+        next_line
+    } else {
+        next_line - 1
+    };
+    format!("#line {} {}\n", line, source)
+}
+
+/// Inserts `preamble` right after the `#version` directive, or at the very
+/// start if there is none, followed by a `#line` directive so that line
+/// numbers in diagnostics still match `source`.
+pub(crate) fn insert_preamble(source: &str, preamble: &str, options: &CompileOptions) -> String {
+    let version = find_version(&code_lines(source));
+    let (offset, next_line) = match version {
+        Some(version) => {
+            let offset = source
+                .split('\n')
+                .take(version.line + 1)
+                .map(|line| line.len() + 1)
+                .sum::<usize>();
+            (offset, version.line + 2)
+        }
+        None => (0, 1),
+    };
+
+    let mut inserted = String::with_capacity(source.len() + preamble.len() + 16);
+    if offset > source.len() {
+        // The `#version` directive is on the last line, without a newline.
+        inserted.push_str(source);
+        inserted.push('\n');
+    } else {
+        inserted.push_str(&source[..offset]);
+    }
+    inserted.push_str(preamble);
+    inserted.push_str(&line_directive(next_line, 0, version, options));
+    if offset < source.len() {
+        inserted.push_str(&source[offset..]);
+    }
+    inserted
+}
+
+/// Storage of a global declaration found by [`declarations`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Storage {
+    /// `uniform` or `buffer`.
+    Resource,
+    Input,
+    Output,
+}
+
+/// A global `uniform`, `buffer`, `in` or `out` declaration.
+#[derive(Debug, Clone)]
+pub(crate) struct Declaration {
+    pub storage: Storage,
+    /// The block name, or the names of the declared variables.
+    pub names: Vec<String>,
+    /// Identifiers inside the layout qualifiers, such as `set` or `std140`.
+    pub layout: Vec<String>,
+    /// Byte offset just inside the parentheses of the first layout qualifier,
+    /// or of the start of the declaration if it has none.
+    insert_at: usize,
+    has_layout: bool,
+}
+
+impl Declaration {
+    pub fn has_layout(&self, id: &str) -> bool {
+        self.layout.iter().any(|layout| layout == id)
+    }
+}
+
+/// Tokens of `source` along with their byte offsets, skipping comments and
+/// preprocessor directives.
+fn tokens(source: &str) -> Vec<(usize, &str)> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line_start = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c == b'\n' {
+            line_start = true;
+            i += 1;
+        } else if c.is_ascii_whitespace() {
+            i += 1;
+        } else if source[i..].starts_with("//") || (c == b'#' && line_start) {
+            // Directives may continue over several lines.
+            while i < bytes.len() && bytes[i] != b'\n' {
+                if bytes[i] == b'\\' && c == b'#' && i + 1 < bytes.len() {
+                    i += 1;
+                }
+                i += 1;
+            }
+        } else if source[i..].starts_with("/*") {
+            i = source[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| i + end + 4);
+        } else {
+            line_start = false;
+            let start = i;
+            if c.is_ascii_alphanumeric() || c == b'_' {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+            } else {
+                i += source[i..].chars().next().map_or(1, char::len_utf8);
+            }
+            tokens.push((start, &source[start..i]));
+        }
+    }
+    tokens
+}
+
+fn is_identifier_token(token: &str) -> bool {
+    token
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+}
+
+/// The global `uniform`, `buffer`, `in` and `out` declarations of a GLSL
+/// shader. Function bodies are skipped, and macros aren't expanded.
+pub(crate) fn declarations(source: &str) -> Vec<Declaration> {
+    let tokens = tokens(source);
+    let mut declarations = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let start = i;
+        let mut depth = 0;
+        let mut layout = Vec::new();
+        let mut layout_at = None;
+        // Tokens outside of parentheses and brackets, except layouts.
+        let mut outer = Vec::new();
+
+        while i < tokens.len() {
+            let token = tokens[i].1;
+            match token {
+                "(" | "[" => depth += 1,
+                ")" | "]" => depth -= 1,
+                ";" | "{" if depth == 0 => break,
+                "layout" if depth == 0 && tokens.get(i + 1).map(|t| t.1) == Some("(") => {
+                    layout_at.get_or_insert(tokens[i + 1].0 + 1);
+                    i += 2;
+                    let mut layout_depth = 1;
+                    while i < tokens.len() && layout_depth > 0 {
+                        match tokens[i].1 {
+                            "(" => layout_depth += 1,
+                            ")" => layout_depth -= 1,
+                            token if is_identifier_token(token) => layout.push(token.to_string()),
+                            _ => {}
+                        }
+                        i += 1;
+                    }
+                    continue;
+                }
+                _ if depth == 0 => outer.push(i),
+                _ => {}
+            }
+            i += 1;
+        }
+
+        let storage = outer.iter().find_map(|&t| match tokens[t].1 {
+            "uniform" | "buffer" => Some(Storage::Resource),
+            "in" => Some(Storage::Input),
+            "out" => Some(Storage::Output),
+            _ => None,
+        });
+        let opens_block = tokens.get(i).map(|t| t.1) == Some("{");
+
+        let mut names = Vec::new();
+        if opens_block {
+            if storage.is_some() {
+                if let Some(&t) = outer.last() {
+                    names.push(tokens[t].1.to_string());
+                }
+            }
+            // Skip the block members or function body.
+            let mut braces = 0;
+            while i < tokens.len() {
+                match tokens[i].1 {
+                    "{" => braces += 1,
+                    "}" => braces -= 1,
+                    _ => {}
+                }
+                i += 1;
+                if braces == 0 {
+                    break;
+                }
+            }
+        } else {
+            // Names are followed by a separator, an array size or an
+            // initializer, which is skipped.
+            let mut initializer = false;
+            for (n, &t) in outer.iter().enumerate() {
+                let next = outer.get(n + 1).map_or(";", |&next| tokens[next].1);
+                let next = if t + 1 < tokens.len() && tokens[t + 1].1 == "[" {
+                    "["
+                } else {
+                    next
+                };
+                match tokens[t].1 {
+                    "=" => initializer = true,
+                    "," => initializer = false,
+                    "uniform" | "buffer" | "in" | "out" => {}
+                    token if !initializer && is_identifier_token(token) => {
+                        if let "," | ";" | "[" | "=" = next {
+                            names.push(token.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+
+        if let (Some(storage), false) = (storage, names.is_empty()) {
+            declarations.push(Declaration {
+                storage,
+                names,
+                layout,
+                insert_at: layout_at.unwrap_or(tokens[start].0),
+                has_layout: layout_at.is_some(),
+            });
+        }
+    }
+    declarations
+}
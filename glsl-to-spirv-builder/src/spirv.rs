@@ -0,0 +1,299 @@
+//! Minimal reader for SPIR-V word streams.
+
+use std::collections::HashMap;
+
+pub(crate) const MAGIC: u32 = 0x0723_0203;
+pub(crate) const HEADER_LEN: usize = 5;
+
+pub(crate) mod op {
+    pub const NAME: u16 = 5;
+    pub const MEMBER_NAME: u16 = 6;
+    pub const ENTRY_POINT: u16 = 15;
+    pub const EXECUTION_MODE: u16 = 16;
+    pub const TYPE_VOID: u16 = 19;
+    pub const TYPE_BOOL: u16 = 20;
+    pub const TYPE_INT: u16 = 21;
+    pub const TYPE_FLOAT: u16 = 22;
+    pub const TYPE_VECTOR: u16 = 23;
+    pub const TYPE_MATRIX: u16 = 24;
+    pub const TYPE_IMAGE: u16 = 25;
+    pub const TYPE_SAMPLER: u16 = 26;
+    pub const TYPE_SAMPLED_IMAGE: u16 = 27;
+    pub const TYPE_ARRAY: u16 = 28;
+    pub const TYPE_RUNTIME_ARRAY: u16 = 29;
+    pub const TYPE_STRUCT: u16 = 30;
+    pub const TYPE_POINTER: u16 = 32;
+    pub const CONSTANT: u16 = 43;
+    pub const CONSTANT_COMPOSITE: u16 = 44;
+    pub const SPEC_CONSTANT_TRUE: u16 = 48;
+    pub const SPEC_CONSTANT_FALSE: u16 = 49;
+    pub const SPEC_CONSTANT: u16 = 50;
+    pub const SPEC_CONSTANT_COMPOSITE: u16 = 51;
+    pub const FUNCTION: u16 = 54;
+    pub const VARIABLE: u16 = 59;
+    pub const DECORATE: u16 = 71;
+    pub const MEMBER_DECORATE: u16 = 72;
+    pub const TYPE_ACCELERATION_STRUCTURE: u16 = 5341;
+}
+
+pub(crate) mod decoration {
+    pub const SPEC_ID: u32 = 1;
+    pub const BLOCK: u32 = 2;
+    pub const BUFFER_BLOCK: u32 = 3;
+    pub const ROW_MAJOR: u32 = 4;
+    pub const ARRAY_STRIDE: u32 = 6;
+    pub const MATRIX_STRIDE: u32 = 7;
+    pub const BUILT_IN: u32 = 11;
+    pub const PATCH: u32 = 15;
+    pub const LOCATION: u32 = 30;
+    pub const COMPONENT: u32 = 31;
+    pub const BINDING: u32 = 33;
+    pub const DESCRIPTOR_SET: u32 = 34;
+    pub const OFFSET: u32 = 35;
+}
+
+pub(crate) mod storage_class {
+    pub const UNIFORM_CONSTANT: u32 = 0;
+    pub const INPUT: u32 = 1;
+    pub const UNIFORM: u32 = 2;
+    pub const OUTPUT: u32 = 3;
+    pub const PUSH_CONSTANT: u32 = 9;
+    pub const STORAGE_BUFFER: u32 = 12;
+}
+
+/// A single instruction, without its leading opcode/word-count word.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Instruction<'a> {
+    pub opcode: u16,
+    pub operands: &'a [u32],
+}
+
+/// Iterates over the instructions of a module, stopping at the first
+/// malformed one.
+pub(crate) fn instructions(words: &[u32]) -> impl Iterator<Item = Instruction<'_>> {
+    let mut rest = if words.len() >= HEADER_LEN && words[0] == MAGIC {
+        &words[HEADER_LEN..]
+    } else {
+        &[]
+    };
+
+    std::iter::from_fn(move || {
+        let first = *rest.first()?;
+        let count = (first >> 16) as usize;
+        if count == 0 || count > rest.len() {
+            rest = &[];
+            return None;
+        }
+
+        let instruction = Instruction {
+            opcode: first as u16,
+            operands: &rest[1..count],
+        };
+        rest = &rest[count..];
+        Some(instruction)
+    })
+}
+
+/// Decodes a nul-terminated literal string, returning it along with the number
+/// of words it occupied.
+pub(crate) fn string(operands: &[u32]) -> (String, usize) {
+    let mut bytes = Vec::new();
+    for (i, word) in operands.iter().enumerate() {
+        for byte in word.to_le_bytes().iter() {
+            if *byte == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), i + 1);
+            }
+            bytes.push(*byte);
+        }
+    }
+    (String::from_utf8_lossy(&bytes).into_owned(), operands.len())
+}
+
+/// Encodes `s` as a nul-terminated literal string.
+pub(crate) fn encode_string(s: &str) -> Vec<u32> {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+        .chunks(4)
+        .map(|chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u32::from_le_bytes(word)
+        })
+        .collect()
+}
+
+/// Name of the first entry point of a module.
+pub(crate) fn entry_point(words: &[u32]) -> Option<String> {
+    instructions(words).find_map(|instruction| match instruction {
+        Instruction {
+            opcode: op::ENTRY_POINT,
+            operands: [_, _, name @ ..],
+        } => Some(string(name).0),
+        _ => None,
+    })
+}
+
+/// Renames every entry point of a module to `name`.
+pub(crate) fn rename_entry_points(words: &[u32], name: &str) -> Vec<u32> {
+    if words.len() < HEADER_LEN || words[0] != MAGIC {
+        return words.to_vec();
+    }
+
+    let name = encode_string(name);
+    let mut renamed = words[..HEADER_LEN].to_vec();
+    for instruction in instructions(words) {
+        match instruction {
+            Instruction {
+                opcode: op::ENTRY_POINT,
+                operands: [model, function, rest @ ..],
+            } => {
+                let (_, name_len) = string(rest);
+                let mut operands = vec![*model, *function];
+                operands.extend_from_slice(&name);
+                operands.extend_from_slice(&rest[name_len..]);
+                push_instruction(&mut renamed, op::ENTRY_POINT, &operands);
+            }
+            Instruction { opcode, operands } => push_instruction(&mut renamed, opcode, operands),
+        }
+    }
+    renamed
+}
+
+/// Appends an instruction to `words`.
+pub(crate) fn push_instruction(words: &mut Vec<u32>, opcode: u16, operands: &[u32]) {
+    let count = operands.len() as u32 + 1;
+    words.push(count << 16 | u32::from(opcode));
+    words.extend_from_slice(operands);
+}
+
+/// Type declarations and integer constants of a module, indexed by result id.
+#[derive(Default)]
+pub(crate) struct Types<'a> {
+    types: HashMap<u32, Instruction<'a>>,
+    constants: HashMap<u32, u32>,
+}
+
+impl<'a> Types<'a> {
+    /// Records `instruction` if it declares a type or a constant.
+    pub fn insert(&mut self, instruction: Instruction<'a>) {
+        match instruction.opcode {
+            op::TYPE_VOID..=op::TYPE_POINTER | op::TYPE_ACCELERATION_STRUCTURE => {
+                if let Some(&id) = instruction.operands.first() {
+                    self.types.insert(id, instruction);
+                }
+            }
+            op::CONSTANT => {
+                if let [_, id, value, ..] = *instruction.operands {
+                    self.constants.insert(id, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn get(&self, id: u32) -> Option<Instruction<'a>> {
+        self.types.get(&id).copied()
+    }
+
+    pub fn constant(&self, id: u32) -> Option<u32> {
+        self.constants.get(&id).copied()
+    }
+
+    /// The type a pointer type points to.
+    pub fn pointee(&self, pointer: u32) -> Option<u32> {
+        match self.get(pointer)? {
+            Instruction {
+                opcode: op::TYPE_POINTER,
+                operands: [_, _, pointee, ..],
+            } => Some(*pointee),
+            _ => None,
+        }
+    }
+
+    /// The element type of an array type.
+    pub fn element(&self, array: u32) -> Option<u32> {
+        match self.get(array)? {
+            Instruction {
+                opcode: op::TYPE_ARRAY,
+                operands: [_, element, ..],
+            }
+            | Instruction {
+                opcode: op::TYPE_RUNTIME_ARRAY,
+                operands: [_, element, ..],
+            } => Some(*element),
+            _ => None,
+        }
+    }
+
+    /// Describes a type using GLSL names, e.g. `vec4` or `float[3]`.
+    pub fn describe(&self, id: u32) -> String {
+        let instruction = match self.get(id) {
+            Some(instruction) => instruction,
+            None => return format!("%{}", id),
+        };
+
+        match (instruction.opcode, instruction.operands) {
+            (op::TYPE_VOID, _) => "void".to_string(),
+            (op::TYPE_BOOL, _) => "bool".to_string(),
+            (op::TYPE_INT, [_, width, signed, ..]) => match (*width, *signed) {
+                (32, 0) => "uint".to_string(),
+                (32, _) => "int".to_string(),
+                (width, 0) => format!("uint{}_t", width),
+                (width, _) => format!("int{}_t", width),
+            },
+            (op::TYPE_FLOAT, [_, width, ..]) => match *width {
+                32 => "float".to_string(),
+                64 => "double".to_string(),
+                width => format!("float{}_t", width),
+            },
+            (op::TYPE_VECTOR, [_, component, count, ..]) => {
+                let prefix = match self.describe(*component).as_str() {
+                    "float" => "",
+                    "double" => "d",
+                    "int" => "i",
+                    "uint" => "u",
+                    "bool" => "b",
+                    other => return format!("{}vec{}", other, count),
+                };
+                format!("{}vec{}", prefix, count)
+            }
+            (op::TYPE_MATRIX, [_, column, columns, ..]) => {
+                let (prefix, rows) = match self.get(*column) {
+                    Some(Instruction {
+                        operands: [_, component, rows, ..],
+                        ..
+                    }) => {
+                        let prefix = if self.describe(*component) == "double" {
+                            "d"
+                        } else {
+                            ""
+                        };
+                        (prefix, *rows)
+                    }
+                    _ => ("", 0),
+                };
+                if rows == *columns {
+                    format!("{}mat{}", prefix, columns)
+                } else {
+                    format!("{}mat{}x{}", prefix, columns, rows)
+                }
+            }
+            (op::TYPE_ARRAY, [_, element, length, ..]) => match self.constant(*length) {
+                Some(length) => format!("{}[{}]", self.describe(*element), length),
+                None => format!("{}[]", self.describe(*element)),
+            },
+            (op::TYPE_RUNTIME_ARRAY, [_, element, ..]) => format!("{}[]", self.describe(*element)),
+            (op::TYPE_STRUCT, [_, members @ ..]) => {
+                let members: Vec<String> = members.iter().map(|m| self.describe(*m)).collect();
+                format!("struct {{ {} }}", members.join(", "))
+            }
+            (op::TYPE_POINTER, [_, _, pointee, ..]) => self.describe(*pointee),
+            (op::TYPE_SAMPLER, _) => "sampler".to_string(),
+            (op::TYPE_IMAGE, _) => "image".to_string(),
+            (op::TYPE_SAMPLED_IMAGE, _) => "sampled image".to_string(),
+            (op::TYPE_ACCELERATION_STRUCTURE, _) => "accelerationStructureEXT".to_string(),
+            _ => format!("%{}", id),
+        }
+    }
+}
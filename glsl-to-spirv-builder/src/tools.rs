@@ -0,0 +1,426 @@
+//! SPIR-V validation, disassembly and assembly through SPIRV-Tools, where
+//! the prebuilt libraries include it.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::{SpirvVersion, TargetEnv};
+
+/// The first problem the SPIR-V validator found in a module.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValidationError {
+    /// Index of the offending instruction, or `None` if the problem is with
+    /// the module as a whole.
+    pub instruction: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.instruction {
+            Some(instruction) => write!(f, "instruction {}: {}", instruction, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// A module that couldn't be disassembled.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DisassembleError {
+    /// Index of the instruction that couldn't be decoded, if known.
+    pub instruction: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for DisassembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.instruction {
+            Some(instruction) => write!(f, "instruction {}: {}", instruction, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl Error for DisassembleError {}
+
+/// Assembly text that couldn't be assembled.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssembleError {
+    /// One based line of the error, if known.
+    pub line: Option<usize>,
+    /// One based column of the error, if known.
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{}: {}", line, column, self.message),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+impl Error for AssembleError {}
+
+/// Options controlling [`disassemble_with_options`].
+///
+/// The defaults match `spirv-dis` without colours: friendly names, indented
+/// instructions and a header comment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DisassembleOptions {
+    pub(crate) friendly_names: bool,
+    pub(crate) offsets: bool,
+    pub(crate) color: bool,
+    pub(crate) indent: bool,
+    pub(crate) header: bool,
+}
+
+impl Default for DisassembleOptions {
+    fn default() -> Self {
+        DisassembleOptions {
+            friendly_names: true,
+            offsets: false,
+            color: false,
+            indent: true,
+            header: true,
+        }
+    }
+}
+
+impl DisassembleOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name ids after their debug names or types, e.g. `%main` instead of
+    /// `%4`.
+    pub fn friendly_names(mut self, friendly_names: bool) -> Self {
+        self.friendly_names = friendly_names;
+        self
+    }
+
+    /// Comment every instruction with its byte offset in the module.
+    pub fn offsets(mut self, offsets: bool) -> Self {
+        self.offsets = offsets;
+        self
+    }
+
+    /// Colour the output with terminal escape codes.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn indent(mut self, indent: bool) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Start with a comment describing the module header.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+}
+
+/// Validates `spirv` for `target_env` with the SPIRV-Tools validator.
+///
+/// SPIRV-Tools is only linked on x86_64 Linux and macOS. Elsewhere this
+/// always fails, as do [`disassemble`] and [`assemble`].
+pub fn validate(spirv: &[u32], target_env: TargetEnv) -> Result<(), ValidationError> {
+    imp::validate(spirv, target_env)
+}
+
+/// Disassembles `spirv` to the text format of `spirv-dis`.
+pub fn disassemble(spirv: &[u32]) -> Result<String, DisassembleError> {
+    disassemble_with_options(spirv, &DisassembleOptions::default())
+}
+
+pub fn disassemble_with_options(
+    spirv: &[u32],
+    options: &DisassembleOptions,
+) -> Result<String, DisassembleError> {
+    imp::disassemble(spirv, options)
+}
+
+/// Assembles text in the format of `spirv-as` into a SPIR-V 1.0 module.
+///
+/// Numeric ids such as `%4` are kept, while named ids such as the friendly
+/// names of [`disassemble`] are given fresh numbers. Disassembly without
+/// friendly names therefore round-trips exactly.
+pub fn assemble(text: &str) -> Result<Vec<u32>, AssembleError> {
+    assemble_with_version(text, SpirvVersion::Spirv1_0)
+}
+
+pub fn assemble_with_version(text: &str, version: SpirvVersion) -> Result<Vec<u32>, AssembleError> {
+    imp::assemble(text, version)
+}
+
+#[cfg(spirv_tools)]
+mod imp {
+    use std::ffi::CStr;
+    use std::os::raw::{c_char, c_int, c_void};
+    use std::ptr;
+
+    use super::{AssembleError, DisassembleError, DisassembleOptions, ValidationError};
+    use crate::{SpirvVersion, TargetEnv};
+
+    const SPV_SUCCESS: c_int = 0;
+
+    const SPV_BINARY_TO_TEXT_OPTION_NONE: u32 = 1;
+    const SPV_BINARY_TO_TEXT_OPTION_COLOR: u32 = 1 << 2;
+    const SPV_BINARY_TO_TEXT_OPTION_INDENT: u32 = 1 << 3;
+    const SPV_BINARY_TO_TEXT_OPTION_SHOW_BYTE_OFFSET: u32 = 1 << 4;
+    const SPV_BINARY_TO_TEXT_OPTION_NO_HEADER: u32 = 1 << 5;
+    const SPV_BINARY_TO_TEXT_OPTION_FRIENDLY_NAMES: u32 = 1 << 6;
+
+    const SPV_TEXT_TO_BINARY_OPTION_PRESERVE_NUMERIC_IDS: u32 = 1 << 1;
+
+    const SPV_ENV_UNIVERSAL_1_0: c_int = 0;
+    const SPV_ENV_UNIVERSAL_1_1: c_int = 2;
+    const SPV_ENV_UNIVERSAL_1_2: c_int = 10;
+    const SPV_ENV_UNIVERSAL_1_3: c_int = 17;
+    const SPV_ENV_UNIVERSAL_1_4: c_int = 20;
+    const SPV_ENV_UNIVERSAL_1_5: c_int = 22;
+    const SPV_ENV_VULKAN_1_0: c_int = 1;
+    const SPV_ENV_OPENGL_4_5: c_int = 9;
+    const SPV_ENV_VULKAN_1_1: c_int = 18;
+    const SPV_ENV_VULKAN_1_1_SPIRV_1_4: c_int = 21;
+
+    #[repr(C)]
+    struct SpvPosition {
+        line: usize,
+        column: usize,
+        index: usize,
+    }
+
+    #[repr(C)]
+    struct SpvDiagnostic {
+        position: SpvPosition,
+        error: *mut c_char,
+        is_text_source: bool,
+    }
+
+    #[repr(C)]
+    struct SpvText {
+        str: *const c_char,
+        length: usize,
+    }
+
+    #[repr(C)]
+    struct SpvBinary {
+        code: *mut u32,
+        word_count: usize,
+    }
+
+    extern "C" {
+        fn spvContextCreate(env: c_int) -> *mut c_void;
+        fn spvContextDestroy(context: *mut c_void);
+        fn spvValidateBinary(
+            context: *const c_void,
+            words: *const u32,
+            num_words: usize,
+            diagnostic: *mut *mut SpvDiagnostic,
+        ) -> c_int;
+        fn spvDiagnosticDestroy(diagnostic: *mut SpvDiagnostic);
+        fn spvBinaryToText(
+            context: *const c_void,
+            words: *const u32,
+            num_words: usize,
+            options: u32,
+            text: *mut *mut SpvText,
+            diagnostic: *mut *mut SpvDiagnostic,
+        ) -> c_int;
+        fn spvTextDestroy(text: *mut SpvText);
+        fn spvTextToBinaryWithOptions(
+            context: *const c_void,
+            text: *const c_char,
+            length: usize,
+            options: u32,
+            binary: *mut *mut SpvBinary,
+            diagnostic: *mut *mut SpvDiagnostic,
+        ) -> c_int;
+        fn spvBinaryDestroy(binary: *mut SpvBinary);
+    }
+
+    /// Takes the message out of a diagnostic, destroying it.
+    unsafe fn take_message(diagnostic: *mut SpvDiagnostic, result: c_int) -> String {
+        if diagnostic.is_null() {
+            return format!("SPIRV-Tools failed with code {}", result);
+        }
+        let message = CStr::from_ptr((*diagnostic).error)
+            .to_string_lossy()
+            .into_owned();
+        spvDiagnosticDestroy(diagnostic);
+        message
+    }
+
+    /// The SPIRV-Tools environment for `target_env`, taking the SPIR-V version
+    /// from the module header since Vulkan 1.1 allows two.
+    fn env(spirv: &[u32], target_env: TargetEnv) -> c_int {
+        match target_env {
+            TargetEnv::Vulkan1_0 => SPV_ENV_VULKAN_1_0,
+            TargetEnv::Vulkan1_1 if spirv.get(1).is_some_and(|v| *v >= 0x0001_0400) => {
+                SPV_ENV_VULKAN_1_1_SPIRV_1_4
+            }
+            TargetEnv::Vulkan1_1 => SPV_ENV_VULKAN_1_1,
+            TargetEnv::OpenGl4_5 => SPV_ENV_OPENGL_4_5,
+        }
+    }
+
+    pub(super) fn validate(spirv: &[u32], target_env: TargetEnv) -> Result<(), ValidationError> {
+        unsafe {
+            let context = spvContextCreate(env(spirv, target_env));
+            let mut diagnostic = ptr::null_mut();
+            let result = spvValidateBinary(context, spirv.as_ptr(), spirv.len(), &mut diagnostic);
+            spvContextDestroy(context);
+
+            if result == SPV_SUCCESS {
+                spvDiagnosticDestroy(diagnostic);
+                return Ok(());
+            }
+            // Problems with the module as a whole are reported at index 0.
+            let instruction = position(diagnostic)
+                .map(|position| position.index)
+                .filter(|&index| index != 0);
+            Err(ValidationError {
+                instruction,
+                message: take_message(diagnostic, result),
+            })
+        }
+    }
+
+    pub(super) fn disassemble(
+        spirv: &[u32],
+        options: &DisassembleOptions,
+    ) -> Result<String, DisassembleError> {
+        let flags = [
+            (
+                options.friendly_names,
+                SPV_BINARY_TO_TEXT_OPTION_FRIENDLY_NAMES,
+            ),
+            (options.offsets, SPV_BINARY_TO_TEXT_OPTION_SHOW_BYTE_OFFSET),
+            (options.color, SPV_BINARY_TO_TEXT_OPTION_COLOR),
+            (options.indent, SPV_BINARY_TO_TEXT_OPTION_INDENT),
+            (!options.header, SPV_BINARY_TO_TEXT_OPTION_NO_HEADER),
+        ];
+        let mut bits = SPV_BINARY_TO_TEXT_OPTION_NONE;
+        for (enabled, flag) in flags.iter() {
+            if *enabled {
+                bits |= flag;
+            }
+        }
+
+        unsafe {
+            let context = spvContextCreate(SPV_ENV_UNIVERSAL_1_5);
+            let mut text = ptr::null_mut();
+            let mut diagnostic = ptr::null_mut();
+            let result = spvBinaryToText(
+                context,
+                spirv.as_ptr(),
+                spirv.len(),
+                bits,
+                &mut text,
+                &mut diagnostic,
+            );
+            spvContextDestroy(context);
+
+            if result != SPV_SUCCESS {
+                spvTextDestroy(text);
+                return Err(DisassembleError {
+                    instruction: position(diagnostic).map(|position| position.index),
+                    message: take_message(diagnostic, result),
+                });
+            }
+            spvDiagnosticDestroy(diagnostic);
+
+            let bytes = std::slice::from_raw_parts((*text).str as *const u8, (*text).length);
+            let disassembly = String::from_utf8_lossy(bytes).into_owned();
+            spvTextDestroy(text);
+            Ok(disassembly)
+        }
+    }
+
+    pub(super) fn assemble(text: &str, version: SpirvVersion) -> Result<Vec<u32>, AssembleError> {
+        let env = match version {
+            SpirvVersion::Spirv1_0 => SPV_ENV_UNIVERSAL_1_0,
+            SpirvVersion::Spirv1_1 => SPV_ENV_UNIVERSAL_1_1,
+            SpirvVersion::Spirv1_2 => SPV_ENV_UNIVERSAL_1_2,
+            SpirvVersion::Spirv1_3 => SPV_ENV_UNIVERSAL_1_3,
+            SpirvVersion::Spirv1_4 => SPV_ENV_UNIVERSAL_1_4,
+            SpirvVersion::Spirv1_5 => SPV_ENV_UNIVERSAL_1_5,
+        };
+
+        unsafe {
+            let context = spvContextCreate(env);
+            let mut binary = ptr::null_mut();
+            let mut diagnostic = ptr::null_mut();
+            let result = spvTextToBinaryWithOptions(
+                context,
+                text.as_ptr() as *const c_char,
+                text.len(),
+                SPV_TEXT_TO_BINARY_OPTION_PRESERVE_NUMERIC_IDS,
+                &mut binary,
+                &mut diagnostic,
+            );
+            spvContextDestroy(context);
+
+            if result != SPV_SUCCESS {
+                spvBinaryDestroy(binary);
+                // SPIRV-Tools counts lines and columns from zero.
+                let position = position(diagnostic);
+                return Err(AssembleError {
+                    line: position.map(|position| position.line + 1),
+                    column: position.map(|position| position.column + 1),
+                    message: take_message(diagnostic, result),
+                });
+            }
+            spvDiagnosticDestroy(diagnostic);
+
+            let words = std::slice::from_raw_parts((*binary).code, (*binary).word_count).to_vec();
+            spvBinaryDestroy(binary);
+            Ok(words)
+        }
+    }
+
+    unsafe fn position<'a>(diagnostic: *const SpvDiagnostic) -> Option<&'a SpvPosition> {
+        diagnostic.as_ref().map(|diagnostic| &diagnostic.position)
+    }
+}
+
+#[cfg(not(spirv_tools))]
+mod imp {
+    use super::{AssembleError, DisassembleError, DisassembleOptions, ValidationError};
+    use crate::{SpirvVersion, TargetEnv};
+
+    const UNAVAILABLE: &str = "SPIRV-Tools is not available on this target";
+
+    pub(super) fn validate(_spirv: &[u32], _target_env: TargetEnv) -> Result<(), ValidationError> {
+        Err(ValidationError {
+            instruction: None,
+            message: UNAVAILABLE.to_string(),
+        })
+    }
+
+    pub(super) fn disassemble(
+        _spirv: &[u32],
+        _options: &DisassembleOptions,
+    ) -> Result<String, DisassembleError> {
+        Err(DisassembleError {
+            instruction: None,
+            message: UNAVAILABLE.to_string(),
+        })
+    }
+
+    pub(super) fn assemble(_text: &str, _version: SpirvVersion) -> Result<Vec<u32>, AssembleError> {
+        Err(AssembleError {
+            line: None,
+            column: None,
+            message: UNAVAILABLE.to_string(),
+        })
+    }
+}
@@ -0,0 +1,328 @@
+//! `glslang::TShader` and `glslang::TProgram` methods, and other glslang
+//! functions, that the C interface of this glslang version doesn't expose.
+//!
+//! They are called through their mangled names, on the object that
+//! `glslang_shader_t` and `glslang_program_t` hold as their first field. Both
+//! the names and that layout were checked against glslang 13.3743, the
+//! revision of the x86_64 Linux and macOS prebuilts. Other targets build
+//! glslang from source or ship other builds, so there these functions fail
+//! with [`CompileError::Unsupported`].
+
+use crate::glslang_c_interface::{glslang_program_t, glslang_shader_t};
+use crate::CompileError;
+
+/// Sets the name of the entry point function, which is also the name of the
+/// SPIR-V entry point. Must be called before the shader is parsed.
+pub(crate) unsafe fn entry_point(
+    shader: *mut glslang_shader_t,
+    name: &str,
+) -> Result<(), CompileError> {
+    imp::entry_point(shader, name)
+}
+
+/// Sets the name of the entry point function in the source, which is renamed
+/// to the entry point name. Must be called before the shader is parsed.
+pub(crate) unsafe fn source_entry_point(
+    shader: *mut glslang_shader_t,
+    name: &str,
+) -> Result<(), CompileError> {
+    imp::source_entry_point(shader, name)
+}
+
+/// Lets glslang assign bindings to resources that don't declare one. Must be
+/// called before the shader is parsed.
+pub(crate) unsafe fn auto_map_bindings(
+    shader: *mut glslang_shader_t,
+    map: bool,
+) -> Result<(), CompileError> {
+    imp::auto_map_bindings(shader, map)
+}
+
+/// Lets glslang assign locations to inputs and outputs that don't declare
+/// one. Must be called before the shader is parsed.
+pub(crate) unsafe fn auto_map_locations(
+    shader: *mut glslang_shader_t,
+    map: bool,
+) -> Result<(), CompileError> {
+    imp::auto_map_locations(shader, map)
+}
+
+/// Adds `base` to the bindings of every resource of a `glslang::TResourceType`.
+/// Must be called before the shader is parsed.
+pub(crate) unsafe fn shift_binding(
+    shader: *mut glslang_shader_t,
+    resource: i32,
+    base: u32,
+) -> Result<(), CompileError> {
+    imp::shift_binding(shader, resource, base)
+}
+
+/// Sets the descriptor set of resources that don't declare one, like
+/// glslangValidator's `--resource-set-binding` with a single set. Must be
+/// called before the shader is parsed.
+pub(crate) unsafe fn resource_set_binding(
+    shader: *mut glslang_shader_t,
+    set: u32,
+) -> Result<(), CompileError> {
+    imp::resource_set_binding(shader, set)
+}
+
+/// Assigns bindings and locations across the linked program with glslang's
+/// default resolver. Must be called after linking and before generating
+/// SPIR-V.
+pub(crate) unsafe fn map_io(program: *mut glslang_program_t) -> Result<bool, CompileError> {
+    imp::map_io(program)
+}
+
+/// Version of glslang's SPIR-V generator, which is bumped whenever the
+/// generated code changes, or `None` where it can't be queried.
+pub(crate) fn spirv_generator_version() -> Option<u32> {
+    imp::spirv_generator_version()
+}
+
+#[cfg(glslang_cpp)]
+mod imp {
+    use std::ffi::CString;
+    use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+    use crate::glslang_c_interface::{glslang_program_t, glslang_shader_t};
+    use crate::CompileError;
+
+    extern "C" {
+        #[link_name = "_ZN7glslang7TShader13setEntryPointEPKc"]
+        fn set_entry_point(shader: *mut c_void, name: *const c_char);
+
+        #[link_name = "_ZN7glslang7TShader19setSourceEntryPointEPKc"]
+        fn set_source_entry_point(shader: *mut c_void, name: *const c_char);
+
+        #[link_name = "_ZN7glslang7TShader18setAutoMapBindingsEb"]
+        fn set_auto_map_bindings(shader: *mut c_void, map: bool);
+
+        #[link_name = "_ZN7glslang7TShader19setAutoMapLocationsEb"]
+        fn set_auto_map_locations(shader: *mut c_void, map: bool);
+
+        #[link_name = "_ZN7glslang7TShader15setShiftBindingENS_13TResourceTypeEj"]
+        fn set_shift_binding(shader: *mut c_void, resource: c_int, base: c_uint);
+
+        #[cfg_attr(
+            target_os = "linux",
+            link_name = "_ZN7glslang7TShader21setResourceSetBindingERKSt6vectorISsSaISsEE"
+        )]
+        #[cfg_attr(
+            target_os = "macos",
+            link_name = "_ZN7glslang7TShader21setResourceSetBindingERKNSt3__16vectorINS1_12basic_stringIcNS1_11char_traitsIcEENS1_9allocatorIcEEEENS6_IS8_EEEE"
+        )]
+        fn set_resource_set_binding(shader: *mut c_void, sets: *const c_void);
+
+        #[link_name = "_ZN7glslang8TProgram5mapIOEPNS_14TIoMapResolverEPNS_9TIoMapperE"]
+        fn program_map_io(program: *mut c_void, resolver: *mut c_void, mapper: *mut c_void)
+            -> bool;
+
+        #[link_name = "_ZN7glslang24GetSpirvGeneratorVersionEv"]
+        fn get_spirv_generator_version() -> c_int;
+    }
+
+    /// The `TShader` wrapped by `shader`.
+    unsafe fn tshader(shader: *mut glslang_shader_t) -> *mut c_void {
+        *(shader as *mut *mut c_void)
+    }
+
+    /// The `TProgram` wrapped by `program`.
+    unsafe fn tprogram(program: *mut glslang_program_t) -> *mut c_void {
+        *(program as *mut *mut c_void)
+    }
+
+    pub unsafe fn entry_point(
+        shader: *mut glslang_shader_t,
+        name: &str,
+    ) -> Result<(), CompileError> {
+        let name = CString::new(name).unwrap();
+        // glslang copies the name.
+        set_entry_point(tshader(shader), name.as_ptr());
+        Ok(())
+    }
+
+    pub unsafe fn source_entry_point(
+        shader: *mut glslang_shader_t,
+        name: &str,
+    ) -> Result<(), CompileError> {
+        let name = CString::new(name).unwrap();
+        set_source_entry_point(tshader(shader), name.as_ptr());
+        Ok(())
+    }
+
+    pub unsafe fn auto_map_bindings(
+        shader: *mut glslang_shader_t,
+        map: bool,
+    ) -> Result<(), CompileError> {
+        set_auto_map_bindings(tshader(shader), map);
+        Ok(())
+    }
+
+    pub unsafe fn auto_map_locations(
+        shader: *mut glslang_shader_t,
+        map: bool,
+    ) -> Result<(), CompileError> {
+        set_auto_map_locations(tshader(shader), map);
+        Ok(())
+    }
+
+    pub unsafe fn shift_binding(
+        shader: *mut glslang_shader_t,
+        resource: i32,
+        base: u32,
+    ) -> Result<(), CompileError> {
+        set_shift_binding(tshader(shader), resource, base);
+        Ok(())
+    }
+
+    pub unsafe fn resource_set_binding(
+        shader: *mut glslang_shader_t,
+        set: u32,
+    ) -> Result<(), CompileError> {
+        // glslang copies the vector.
+        with_string_vector(&set.to_string(), |sets| {
+            set_resource_set_binding(tshader(shader), sets)
+        });
+        Ok(())
+    }
+
+    pub unsafe fn map_io(program: *mut glslang_program_t) -> Result<bool, CompileError> {
+        Ok(program_map_io(
+            tprogram(program),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        ))
+    }
+
+    pub fn spirv_generator_version() -> Option<u32> {
+        Some(unsafe { get_spirv_generator_version() as u32 })
+    }
+
+    /// The layout of `std::vector` in both libstdc++ and libc++.
+    #[repr(C)]
+    struct Vector<T> {
+        begin: *const T,
+        end: *const T,
+        end_of_storage: *const T,
+    }
+
+    impl<T> Vector<T> {
+        fn of_one(element: &T) -> Self {
+            let begin: *const T = element;
+            let end = begin.wrapping_add(1);
+            Vector {
+                begin,
+                end,
+                end_of_storage: end,
+            }
+        }
+    }
+
+    /// The shared representation of libstdc++'s copy-on-write `std::string`,
+    /// which the Linux prebuilts were built with. The string itself is a
+    /// pointer to `data`.
+    #[cfg(target_os = "linux")]
+    #[repr(C)]
+    struct StringRep {
+        length: usize,
+        capacity: usize,
+        // Negative marks the string as unshareable, so copies clone it
+        // instead of counting references to memory glslang doesn't own.
+        refcount: i32,
+        _padding: u32,
+        data: [u8; 16],
+    }
+
+    /// Calls `f` with a `std::vector<std::string>` holding `value`.
+    #[cfg(target_os = "linux")]
+    unsafe fn with_string_vector<R>(value: &str, f: impl FnOnce(*const c_void) -> R) -> R {
+        let mut rep = StringRep {
+            length: value.len(),
+            capacity: value.len(),
+            refcount: -1,
+            _padding: 0,
+            data: [0; 16],
+        };
+        // Leaves room for the terminating NUL.
+        rep.data[..value.len()].copy_from_slice(value.as_bytes());
+        let string = rep.data.as_ptr();
+        let vector = Vector::of_one(&string);
+        f(&vector as *const Vector<_> as *const c_void)
+    }
+
+    /// libc++'s `std::string` in its short form, which the macOS prebuilts
+    /// were built with: the length shifted left by one, followed by the
+    /// NUL-terminated characters.
+    #[cfg(target_os = "macos")]
+    #[repr(C, align(8))]
+    struct ShortString([u8; 24]);
+
+    /// Calls `f` with a `std::vector<std::string>` holding `value`.
+    #[cfg(target_os = "macos")]
+    unsafe fn with_string_vector<R>(value: &str, f: impl FnOnce(*const c_void) -> R) -> R {
+        let mut string = ShortString([0; 24]);
+        // Leaves room for the terminating NUL.
+        string.0[0] = (value.len() << 1) as u8;
+        string.0[1..=value.len()].copy_from_slice(value.as_bytes());
+        let vector = Vector::of_one(&string);
+        f(&vector as *const Vector<_> as *const c_void)
+    }
+}
+
+#[cfg(not(glslang_cpp))]
+mod imp {
+    use crate::glslang_c_interface::{glslang_program_t, glslang_shader_t};
+    use crate::CompileError;
+
+    fn unsupported(function: &str) -> CompileError {
+        CompileError::Unsupported {
+            message: format!("glslang's {} can't be called on this target", function),
+        }
+    }
+
+    pub unsafe fn entry_point(_: *mut glslang_shader_t, _: &str) -> Result<(), CompileError> {
+        Err(unsupported("TShader::setEntryPoint"))
+    }
+
+    pub unsafe fn source_entry_point(
+        _: *mut glslang_shader_t,
+        _: &str,
+    ) -> Result<(), CompileError> {
+        Err(unsupported("TShader::setSourceEntryPoint"))
+    }
+
+    pub unsafe fn auto_map_bindings(_: *mut glslang_shader_t, _: bool) -> Result<(), CompileError> {
+        Err(unsupported("TShader::setAutoMapBindings"))
+    }
+
+    pub unsafe fn auto_map_locations(
+        _: *mut glslang_shader_t,
+        _: bool,
+    ) -> Result<(), CompileError> {
+        Err(unsupported("TShader::setAutoMapLocations"))
+    }
+
+    pub unsafe fn shift_binding(
+        _: *mut glslang_shader_t,
+        _: i32,
+        _: u32,
+    ) -> Result<(), CompileError> {
+        Err(unsupported("TShader::setShiftBinding"))
+    }
+
+    pub unsafe fn resource_set_binding(
+        _: *mut glslang_shader_t,
+        _: u32,
+    ) -> Result<(), CompileError> {
+        Err(unsupported("TShader::setResourceSetBinding"))
+    }
+
+    pub unsafe fn map_io(_: *mut glslang_program_t) -> Result<bool, CompileError> {
+        Err(unsupported("TProgram::mapIO"))
+    }
+
+    pub fn spirv_generator_version() -> Option<u32> {
+        None
+    }
+}
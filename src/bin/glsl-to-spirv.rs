@@ -0,0 +1,372 @@
+//! Compiles GLSL shaders to SPIR-V from the command line.
+
+extern crate bevy_glsl_to_spirv;
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use bevy_glsl_to_spirv::{
+    Block, BlockMember, CompileOptions, Diagnostic, FileIncludeResolver, Reflection, ShaderType,
+    TargetEnv,
+};
+
+const USAGE: &str = "\
+Usage: glsl-to-spirv [options] <file>...
+
+Compiles each file to SPIR-V, written next to it with `.spv` appended.
+
+Options:
+  -S <stage>           Stage of the shaders: vert, frag, geom, tesc, tese, comp,
+                       task, mesh, rgen, rint, rahit, rchit, rmiss or rcall.
+                       By default it is taken from the file extension, such
+                       as `.frag` or `.frag.glsl`.
+  -D <name>[=<value>]  Defines a macro.
+  -I <dir>             Adds a directory to search for included files.
+  --target-env <env>   vulkan1.0 (default), vulkan1.1 or opengl4.5.
+  -o <path>            Output file. Only allowed with a single input.
+  --disassemble        Prints the disassembled SPIR-V.
+  --reflect            Prints the resources and interface of the SPIR-V.
+  -h, --help           Prints this message.
+";
+
+struct Args {
+    inputs: Vec<PathBuf>,
+    stage: Option<ShaderType>,
+    defines: Vec<(String, Option<String>)>,
+    include_dirs: Vec<PathBuf>,
+    target_env: TargetEnv,
+    output: Option<PathBuf>,
+    disassemble: bool,
+    reflect: bool,
+}
+
+fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("glsl-to-spirv: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut failed = false;
+    for input in &args.inputs {
+        if let Err(message) = compile(input, &args) {
+            eprint!("{}", message);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        inputs: Vec::new(),
+        stage: None,
+        defines: Vec::new(),
+        include_dirs: Vec::new(),
+        target_env: TargetEnv::Vulkan1_0,
+        output: None,
+        disassemble: false,
+        reflect: false,
+    };
+
+    while let Some(arg) = args.next() {
+        // Takes the value of `-X value` or `-Xvalue`.
+        let mut value = |flag: &str| match &arg[flag.len()..] {
+            "" => args.next().ok_or_else(|| format!("{} needs a value", flag)),
+            attached => Ok(attached.to_string()),
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--disassemble" => parsed.disassemble = true,
+            "--reflect" => parsed.reflect = true,
+            "--target-env" => {
+                parsed.target_env = match value("--target-env")?.as_str() {
+                    "vulkan1.0" => TargetEnv::Vulkan1_0,
+                    "vulkan1.1" => TargetEnv::Vulkan1_1,
+                    "opengl4.5" => TargetEnv::OpenGl4_5,
+                    env => return Err(format!("unknown target environment '{}'", env)),
+                }
+            }
+            _ if arg.starts_with("-S") => {
+                let stage = value("-S")?;
+                parsed.stage =
+                    Some(stage_from_name(&stage).ok_or(format!("unknown stage '{}'", stage))?);
+            }
+            _ if arg.starts_with("-D") => {
+                let define = value("-D")?;
+                parsed.defines.push(match define.split_once('=') {
+                    Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                    None => (define, None),
+                });
+            }
+            _ if arg.starts_with("-I") => parsed.include_dirs.push(value("-I")?.into()),
+            _ if arg.starts_with("-o") => parsed.output = Some(value("-o")?.into()),
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("unknown option '{}'", arg));
+            }
+            _ => parsed.inputs.push(arg.into()),
+        }
+    }
+
+    if parsed.inputs.is_empty() {
+        return Err("no input files".to_string());
+    }
+    if parsed.output.is_some() && parsed.inputs.len() > 1 {
+        return Err("-o can't be used with several input files".to_string());
+    }
+    Ok(Some(parsed))
+}
+
+fn stage_from_name(name: &str) -> Option<ShaderType> {
+    Some(match name {
+        "vert" => ShaderType::Vertex,
+        "frag" => ShaderType::Fragment,
+        "geom" => ShaderType::Geometry,
+        "tesc" => ShaderType::TessellationControl,
+        "tese" => ShaderType::TessellationEvaluation,
+        "comp" => ShaderType::Compute,
+        "task" => ShaderType::Task,
+        "mesh" => ShaderType::Mesh,
+        "rgen" => ShaderType::RayGeneration,
+        "rint" => ShaderType::Intersection,
+        "rahit" => ShaderType::AnyHit,
+        "rchit" => ShaderType::ClosestHit,
+        "rmiss" => ShaderType::Miss,
+        "rcall" => ShaderType::Callable,
+        _ => return None,
+    })
+}
+
+/// The stage of `shader.frag` or `shader.frag.glsl`.
+fn stage_from_path(path: &Path) -> Option<ShaderType> {
+    let extension = path.extension()?.to_str()?;
+    if extension == "glsl" {
+        return stage_from_path(Path::new(path.file_stem()?));
+    }
+    stage_from_name(extension)
+}
+
+/// Compiles `input`, returning the messages to print on failure.
+fn compile(input: &Path, args: &Args) -> Result<(), String> {
+    let name = input.display().to_string();
+    let stage = args
+        .stage
+        .or_else(|| stage_from_path(input))
+        .ok_or_else(|| {
+            format!(
+                "{}: can't tell the stage from the extension, use -S\n",
+                name
+            )
+        })?;
+    let code = fs::read_to_string(input).map_err(|e| format!("{}: {}\n", name, e))?;
+
+    let mut resolver = FileIncludeResolver::new();
+    for dir in &args.include_dirs {
+        resolver = resolver.include_dir(dir);
+    }
+    let mut options = CompileOptions::new()
+        .target_env(args.target_env)
+        .source_name(name.as_str())
+        .include_resolver(resolver);
+    for (define, value) in &args.defines {
+        options = options.define(define.as_str(), value.as_deref());
+    }
+
+    let output = match bevy_glsl_to_spirv::compile_with_options(&code, stage, &options) {
+        Ok(output) => output,
+        Err(error) => {
+            let mut message = format_diagnostics(&name, error.includes(), error.diagnostics());
+            if message.is_empty() {
+                message = format!("{}: {}\n", name, error);
+            }
+            return Err(message);
+        }
+    };
+    eprint!(
+        "{}",
+        format_diagnostics(&name, &output.includes, &output.diagnostics)
+    );
+
+    let path = match &args.output {
+        Some(path) => path.clone(),
+        None => {
+            let mut path = input.as_os_str().to_owned();
+            path.push(".spv");
+            path.into()
+        }
+    };
+    let bytes: Vec<u8> = output
+        .spirv
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    fs::write(&path, bytes).map_err(|e| format!("{}: {}\n", path.display(), e))?;
+
+    if args.disassemble {
+        let text = bevy_glsl_to_spirv::disassemble(&output.spirv)
+            .map_err(|e| format!("{}: {}\n", name, e))?;
+        print!("{}", text);
+    }
+    if args.reflect {
+        print!("{}", format_reflection(&output.reflection));
+    }
+    Ok(())
+}
+
+/// Formats diagnostics as `file:line: severity: message`, one per line.
+fn format_diagnostics(name: &str, includes: &[String], diagnostics: &[Diagnostic]) -> String {
+    let mut text = String::new();
+    for diagnostic in diagnostics {
+        // Source 0 is the compiled file, the others are its includes.
+        let file = match diagnostic.source {
+            Some(0) | None => name,
+            Some(source) => includes
+                .get(source as usize - 1)
+                .map_or("<include>", String::as_str),
+        };
+        match diagnostic.line {
+            Some(line) => write!(text, "{}:{}: ", file, line),
+            None => write!(text, "{}: ", file),
+        }
+        .unwrap();
+        write!(text, "{}: ", diagnostic.severity).unwrap();
+        if let Some(token) = &diagnostic.token {
+            write!(text, "'{}' : ", token).unwrap();
+        }
+        writeln!(text, "{}", diagnostic.message).unwrap();
+    }
+    text
+}
+
+fn format_reflection(reflection: &Reflection) -> String {
+    let mut text = String::new();
+    let mut section = |title: &str, lines: Vec<String>| {
+        if !lines.is_empty() {
+            writeln!(text, "{}:", title).unwrap();
+            for line in lines {
+                writeln!(text, "  {}", line).unwrap();
+            }
+        }
+    };
+
+    let blocks = |blocks: &[Block]| {
+        let mut lines = Vec::new();
+        for block in blocks {
+            lines.push(format!(
+                "set {} binding {}: {}{} ({} bytes)",
+                block.set,
+                block.binding,
+                block.name,
+                array(block.count),
+                block.size
+            ));
+            lines.extend(members(&block.members));
+        }
+        lines
+    };
+    section("uniform blocks", blocks(&reflection.uniform_blocks));
+    section("storage buffers", blocks(&reflection.storage_buffers));
+    section(
+        "resources",
+        reflection
+            .resources
+            .iter()
+            .map(|resource| {
+                format!(
+                    "set {} binding {}: {}{} ({:?})",
+                    resource.set,
+                    resource.binding,
+                    resource.name,
+                    array(resource.count),
+                    resource.kind
+                )
+            })
+            .collect(),
+    );
+
+    let interface = |variables: &[bevy_glsl_to_spirv::InterfaceVariable]| {
+        variables
+            .iter()
+            .map(|variable| {
+                format!(
+                    "location {}: {} {}",
+                    variable.location, variable.ty, variable.name
+                )
+            })
+            .collect()
+    };
+    section("inputs", interface(&reflection.inputs));
+    section("outputs", interface(&reflection.outputs));
+
+    if let Some(range) = &reflection.push_constants {
+        let mut lines = vec![format!(
+            "{} ({} bytes at offset {})",
+            range.name, range.size, range.offset
+        )];
+        lines.extend(members(&range.members));
+        section("push constants", lines);
+    }
+    section(
+        "specialization constants",
+        reflection
+            .specialization_constants
+            .iter()
+            .map(|constant| {
+                format!(
+                    "id {}: {} {} = {}",
+                    constant.id, constant.ty, constant.name, constant.default
+                )
+            })
+            .collect(),
+    );
+    section(
+        "entry points",
+        reflection
+            .entry_points
+            .iter()
+            .map(|entry_point| {
+                let mut line = entry_point.name.clone();
+                if let Some(stage) = entry_point.stage {
+                    write!(line, " ({:?})", stage).unwrap();
+                }
+                if let Some([x, y, z]) = entry_point.workgroup_size {
+                    write!(line, " local size {} {} {}", x, y, z).unwrap();
+                }
+                line
+            })
+            .collect(),
+    );
+    text
+}
+
+fn members(members: &[BlockMember]) -> Vec<String> {
+    members
+        .iter()
+        .map(|member| {
+            format!(
+                "  {} {} at offset {} ({} bytes)",
+                member.ty, member.name, member.offset, member.size
+            )
+        })
+        .collect()
+}
+
+fn array(count: u32) -> String {
+    if count == 1 {
+        String::new()
+    } else {
+        format!("[{}]", count)
+    }
+}
@@ -23,3 +23,1495 @@ void main() {
 
     bevy_glsl_to_spirv::compile(shader, bevy_glsl_to_spirv::ShaderType::Fragment, None).unwrap();
 }
+
+#[test]
+fn parse_error() {
+    let shader = r#"
+#version 330
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = undeclared;
+}
+"#;
+
+    let err = bevy_glsl_to_spirv::compile(shader, bevy_glsl_to_spirv::ShaderType::Fragment, None)
+        .unwrap_err();
+    match err {
+        bevy_glsl_to_spirv::CompileError::Parse { ref info_log, .. } => {
+            assert!(info_log.contains("undeclared"))
+        }
+        _ => panic!("unexpected error: {:?}", err),
+    }
+
+    let diagnostic = &err.diagnostics()[0];
+    assert_eq!(diagnostic.severity, bevy_glsl_to_spirv::Severity::Error);
+    assert_eq!(diagnostic.line, Some(7));
+    assert_eq!(diagnostic.token.as_deref(), Some("undeclared"));
+}
+
+#[test]
+fn parse_info_log() {
+    use bevy_glsl_to_spirv::{Diagnostic, Severity};
+
+    let log = "WARNING: 0:3: '#extension' : extension not supported: GL_foo\n\
+               ERROR: 1:12: 'foo' : undeclared identifier\n\
+               ERROR: Linking fragment stage: Missing entry point: Each stage requires one entry point\n\
+               ERROR: 2 compilation errors.  No code generated.\n";
+
+    let diagnostics = Diagnostic::parse_log(log);
+    assert_eq!(diagnostics.len(), 3);
+
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[0].source, Some(0));
+    assert_eq!(diagnostics[0].line, Some(3));
+    assert_eq!(diagnostics[0].token.as_deref(), Some("#extension"));
+    assert_eq!(diagnostics[0].message, "extension not supported: GL_foo");
+
+    assert_eq!(diagnostics[1].source, Some(1));
+    assert_eq!(diagnostics[1].line, Some(12));
+    assert_eq!(diagnostics[1].message, "undeclared identifier");
+
+    assert_eq!(diagnostics[2].line, None);
+    assert_eq!(diagnostics[2].token, None);
+    assert_eq!(
+        diagnostics[2].message,
+        "Linking fragment stage: Missing entry point: Each stage requires one entry point"
+    );
+}
+
+#[test]
+fn warnings_on_success() {
+    let shader = r#"
+#version 330
+#extension GL_EXT_does_not_exist : enable
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(1.0);
+}
+"#;
+
+    let output =
+        bevy_glsl_to_spirv::compile(shader, bevy_glsl_to_spirv::ShaderType::Fragment, None)
+            .unwrap();
+    assert!(!output.spirv.is_empty());
+
+    let warning = &output.diagnostics[0];
+    assert_eq!(warning.severity, bevy_glsl_to_spirv::Severity::Warning);
+    assert_eq!(warning.line, Some(3));
+}
+
+#[test]
+fn compile_options() {
+    use bevy_glsl_to_spirv::{CompileOptions, ShaderType, SpirvVersion, TargetEnv};
+
+    let shader = r#"
+#version 450
+#extension GL_KHR_shader_subgroup_basic : require
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(float(gl_SubgroupSize));
+}
+"#;
+
+    let options = CompileOptions::new()
+        .target_env(TargetEnv::Vulkan1_1)
+        .spirv_version(SpirvVersion::Spirv1_3);
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Fragment, &options).unwrap();
+
+    // SPIR-V version is the second word of the header.
+    assert_eq!(output.spirv[1], 0x0001_0300);
+
+    let output = bevy_glsl_to_spirv::compile(shader, ShaderType::Fragment, None).unwrap();
+    assert_eq!(output.spirv[1], 0x0001_0000);
+}
+
+#[test]
+fn program_linking() {
+    use bevy_glsl_to_spirv::{CompileError, CompileOptions, Severity, ShaderType};
+
+    let vertex = r#"
+#version 450
+
+layout(location = 0) out vec3 v_color;
+
+void main() {
+    v_color = vec3(1.0);
+    gl_Position = vec4(0.0);
+}
+"#;
+
+    let fragment = r#"
+#version 450
+
+layout(location = 0) in vec3 v_color;
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(v_color, 1.0);
+}
+"#;
+
+    let output = bevy_glsl_to_spirv::compile_program(
+        &[
+            (vertex, ShaderType::Vertex),
+            (fragment, ShaderType::Fragment),
+        ],
+        &CompileOptions::new(),
+    )
+    .unwrap();
+    assert_eq!(output.spirv.len(), 2);
+    assert_ne!(
+        output.spirv[&ShaderType::Vertex],
+        output.spirv[&ShaderType::Fragment]
+    );
+
+    let mismatched = fragment.replace("in vec3 v_color", "in vec4 v_color");
+    let mismatched = mismatched.replace("vec4(v_color, 1.0)", "v_color");
+    let err = bevy_glsl_to_spirv::compile_program(
+        &[
+            (vertex, ShaderType::Vertex),
+            (&mismatched, ShaderType::Fragment),
+        ],
+        &CompileOptions::new(),
+    )
+    .unwrap_err();
+    match err {
+        CompileError::Link { ref info_log, .. } => assert!(info_log.contains("location 0")),
+        _ => panic!("unexpected error: {:?}", err),
+    }
+
+    // Block members have locations of their own, and an input no output
+    // writes is only undefined.
+    let block = r#"
+#version 450
+
+layout(location = 0) out VertexData {
+    vec2 uv;
+    vec3 normal;
+    layout(location = 4) float depth;
+};
+
+void main() {
+    uv = vec2(0.0);
+    normal = vec3(0.0);
+    depth = 0.0;
+    gl_Position = vec4(0.0);
+}
+"#;
+    let members = r#"
+#version 450
+
+layout(location = 0) in vec2 uv;
+layout(location = 1) in vec3 normal;
+layout(location = 4) in float depth;
+layout(location = 5) in vec4 unwritten;
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(uv, depth, 1.0) + vec4(normal, 0.0) + unwritten;
+}
+"#;
+    let output = bevy_glsl_to_spirv::compile_program(
+        &[(block, ShaderType::Vertex), (members, ShaderType::Fragment)],
+        &CompileOptions::new(),
+    )
+    .unwrap();
+    let warnings: Vec<_> = output
+        .diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Warning)
+        .collect();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].message.contains("'unwritten' at location 5"));
+
+    let mismatched = members
+        .replace("in vec3 normal", "in vec4 normal")
+        .replace("vec4(normal, 0.0)", "normal");
+    let err = bevy_glsl_to_spirv::compile_program(
+        &[
+            (block, ShaderType::Vertex),
+            (&mismatched, ShaderType::Fragment),
+        ],
+        &CompileOptions::new(),
+    )
+    .unwrap_err();
+    assert_eq!(err.diagnostics().len(), 1);
+    assert!(err.diagnostics()[0]
+        .message
+        .contains("'vec3' output 'VertexData.normal' vs 'vec4' input 'normal'"));
+}
+
+#[test]
+fn includes() {
+    use bevy_glsl_to_spirv::{
+        CompileOptions, FileIncludeResolver, IncludeResolver, IncludeType, ResolvedInclude,
+        ShaderType,
+    };
+    use std::collections::HashMap;
+
+    struct MapResolver(HashMap<&'static str, &'static str>);
+
+    impl IncludeResolver for MapResolver {
+        fn resolve(
+            &self,
+            name: &str,
+            _ty: IncludeType,
+            _requesting: &str,
+            _depth: usize,
+        ) -> Result<ResolvedInclude, String> {
+            let content = self.0.get(name).ok_or("not found")?;
+            Ok(ResolvedInclude {
+                name: name.to_string(),
+                content: content.to_string(),
+            })
+        }
+    }
+
+    let shader = r#"
+#version 450
+#include "lighting.glsl"
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = light();
+}
+"#;
+
+    let mut files = HashMap::new();
+    files.insert(
+        "lighting.glsl",
+        "#include <common.glsl>\nvec4 light() { return AMBIENT; }\n",
+    );
+    files.insert("common.glsl", "#define AMBIENT vec4(0.1)\n");
+    let options = CompileOptions::new().include_resolver(MapResolver(files.clone()));
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Fragment, &options).unwrap();
+    assert_eq!(output.includes, ["lighting.glsl", "common.glsl"]);
+
+    // Errors point into the included file, and lines after the include are unaffected.
+    files.insert("common.glsl", "\nfloat broken = ;\n");
+    let options = CompileOptions::new().include_resolver(MapResolver(files.clone()));
+    let err = bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Fragment, &options)
+        .unwrap_err();
+    assert_eq!(err.diagnostics()[0].source, Some(2));
+    assert_eq!(err.diagnostics()[0].line, Some(2));
+
+    let broken = shader.replace("light();", "light() + missing;");
+    files.insert("common.glsl", "#define AMBIENT vec4(0.1)\n");
+    let options = CompileOptions::new().include_resolver(MapResolver(files));
+    let err = bevy_glsl_to_spirv::compile_with_options(&broken, ShaderType::Fragment, &options)
+        .unwrap_err();
+    assert_eq!(err.diagnostics()[0].source, Some(0));
+    assert_eq!(err.diagnostics()[0].line, Some(8));
+
+    let dir = std::env::temp_dir().join("bevy-glsl-to-spirv-includes");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("lighting.glsl"),
+        "vec4 light() { return vec4(1.0); }\n",
+    )
+    .unwrap();
+    let options =
+        CompileOptions::new().include_resolver(FileIncludeResolver::new().include_dir(&dir));
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Fragment, &options).unwrap();
+    assert_eq!(
+        output.includes,
+        [dir.join("lighting.glsl").to_string_lossy()]
+    );
+
+    let err = bevy_glsl_to_spirv::compile_with_options(
+        &shader.replace("lighting", "missing"),
+        ShaderType::Fragment,
+        &options,
+    )
+    .unwrap_err();
+    assert_eq!(err.diagnostics()[0].token.as_deref(), Some("missing.glsl"));
+    assert_eq!(err.diagnostics()[0].line, Some(3));
+}
+
+#[test]
+fn preprocess_only() {
+    let shader = r#"
+#version 450
+#define SCALE 2.0
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+#ifdef TINT
+    f_color = vec4(SCALE * TINT);
+#else
+    f_color = vec4(SCALE);
+#endif
+}
+"#;
+
+    let preprocessed = bevy_glsl_to_spirv::preprocess(
+        shader,
+        bevy_glsl_to_spirv::ShaderType::Fragment,
+        Some(&["TINT=0.5".to_string()]),
+    )
+    .unwrap();
+    assert!(preprocessed.contains("vec4(2.0 * 0.5)"));
+    assert!(!preprocessed.contains("#ifdef"));
+    assert!(!preprocessed.contains("vec4(SCALE)"));
+}
+
+#[test]
+fn defines() {
+    use bevy_glsl_to_spirv::{CompileError, CompileOptions, Profile, ShaderType};
+
+    let shader = r#"// Based on the #version 110 shader.
+#version 450
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(SCALE) * undeclared;
+}
+"#;
+
+    let options = CompileOptions::new()
+        .define("SCALE", Some("2.0"))
+        .define("UNUSED", None);
+    let err = bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Fragment, &options)
+        .unwrap_err();
+    assert_eq!(err.diagnostics()[0].token.as_deref(), Some("undeclared"));
+    assert_eq!(err.diagnostics()[0].line, Some(7));
+
+    let fixed = shader.replace(" * undeclared", "");
+    bevy_glsl_to_spirv::compile_with_options(&fixed, ShaderType::Fragment, &options).unwrap();
+
+    // Without a #version the definitions still apply.
+    let unversioned = fixed.replace("#version 450", "");
+    let options = options.default_version(450, Profile::None);
+    bevy_glsl_to_spirv::compile_with_options(&unversioned, ShaderType::Fragment, &options).unwrap();
+
+    let options = CompileOptions::new().define("2SCALE", Some("2.0"));
+    match bevy_glsl_to_spirv::compile_with_options(&fixed, ShaderType::Fragment, &options) {
+        Err(CompileError::InvalidDefine { name, .. }) => assert_eq!(name, "2SCALE"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let options = CompileOptions::new().define("SCALE", Some("2.0\n#error"));
+    assert!(
+        bevy_glsl_to_spirv::compile_with_options(&fixed, ShaderType::Fragment, &options).is_err()
+    );
+
+    // Any whitespace separates the name from the value, even multi-byte.
+    let defs = ["SCALE\u{a0}2.0".to_string(), "UNUSED\u{3000}".to_string()];
+    let options = CompileOptions::new().shader_defs(&defs);
+    bevy_glsl_to_spirv::compile_with_options(&fixed, ShaderType::Fragment, &options).unwrap();
+}
+
+#[test]
+fn hlsl() {
+    use bevy_glsl_to_spirv::{CompileOptions, Severity, ShaderType, SourceLanguage};
+
+    let shader = r#"
+cbuffer Globals {
+    float4 tint;
+};
+
+float4 PSMain(float4 position : SV_Position) : SV_Target {
+    return position * tint;
+}
+"#;
+
+    let options = CompileOptions::new()
+        .source_language(SourceLanguage::Hlsl)
+        .entry_point("PSMain")
+        .hlsl_offsets(true);
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Fragment, &options).unwrap();
+    assert!(output.diagnostics.is_empty());
+
+    // glslang only warns when the entry point is missing.
+    let options = options.entry_point("main");
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Fragment, &options).unwrap();
+    assert_eq!(output.diagnostics[0].severity, Severity::Warning);
+    assert!(output.diagnostics[0]
+        .message
+        .contains("Entry point not found"));
+}
+
+#[test]
+fn entry_points() {
+    use bevy_glsl_to_spirv::{CompileOptions, ShaderType, SourceLanguage};
+
+    let shader = r#"
+#version 450
+
+layout(location = 0) out vec4 f_color;
+
+void vs_main() {
+    f_color = vec4(0.5);
+}
+
+void fs_main() {
+    f_color = vec4(1.0);
+}
+"#;
+
+    let options = CompileOptions::new()
+        .entry_point("fragment")
+        .source_entry_point("fs_main");
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Fragment, &options).unwrap();
+    assert_eq!(output.entry_point.as_deref(), Some("fragment"));
+    assert!(output.diagnostics.is_empty());
+
+    let options = CompileOptions::new().source_entry_point("vs_main");
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Vertex, &options).unwrap();
+    assert_eq!(output.entry_point.as_deref(), Some("main"));
+
+    let hlsl = "float4 PSMain() : SV_Target { return 1.0; }\n";
+    let options = CompileOptions::new()
+        .source_language(SourceLanguage::Hlsl)
+        .source_entry_point("PSMain");
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(hlsl, ShaderType::Fragment, &options).unwrap();
+    assert_eq!(output.entry_point.as_deref(), Some("main"));
+}
+
+#[test]
+fn mesh_and_ray_tracing_stages() {
+    use bevy_glsl_to_spirv::{CompileOptions, ShaderStages, ShaderType};
+
+    let mesh = r#"
+#version 450
+#extension GL_NV_mesh_shader : require
+
+layout(local_size_x = 1) in;
+layout(triangles, max_vertices = 3, max_primitives = 1) out;
+
+layout(location = 0) out vec3 v_color[];
+
+void main() {
+    for (int i = 0; i < 3; i++) {
+        gl_MeshVerticesNV[i].gl_Position = vec4(float(i), 0.0, 0.0, 1.0);
+        v_color[i] = vec3(1.0);
+        gl_PrimitiveIndicesNV[i] = i;
+    }
+    gl_PrimitiveCountNV = 1;
+}
+"#;
+
+    let fragment = r#"
+#version 450
+
+layout(location = 0) in vec3 v_color;
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(v_color, 1.0);
+}
+"#;
+
+    let output = bevy_glsl_to_spirv::compile_program(
+        &[(mesh, ShaderType::Mesh), (fragment, ShaderType::Fragment)],
+        &CompileOptions::new(),
+    )
+    .unwrap();
+    assert_eq!(output.stages(), ShaderType::Mesh | ShaderType::Fragment);
+    assert!(ShaderStages::MESH_PIPELINE.contains(output.stages()));
+    assert!(!ShaderStages::VERTEX_PIPELINE.contains(output.stages()));
+
+    let raygen = r#"
+#version 460
+#extension GL_NV_ray_tracing : require
+
+layout(location = 0) rayPayloadNV vec4 payload;
+layout(binding = 0) uniform accelerationStructureNV scene;
+
+void main() {
+    traceNV(scene, 0, 0xff, 0, 0, 0, vec3(0.0), 0.0, vec3(0.0, 0.0, 1.0), 100.0, 0);
+}
+"#;
+
+    bevy_glsl_to_spirv::compile(raygen, ShaderType::RayGeneration, None).unwrap();
+}
+
+#[test]
+fn resource_limits() {
+    use bevy_glsl_to_spirv::{CompileOptions, ResourceLimits, ShaderType};
+
+    let shader = r#"
+#version 450
+
+layout(local_size_x = 1, local_size_y = 1, local_size_z = 128) in;
+
+void main() {}
+"#;
+
+    let err = bevy_glsl_to_spirv::compile(shader, ShaderType::Compute, None).unwrap_err();
+    assert!(err.info_log().contains("local_size"));
+
+    let limits = ResourceLimits::new().max_compute_work_group_size_z(128);
+    assert_eq!(limits.max_compute_work_group_size_z, 128);
+    let options = CompileOptions::new().resource_limits(limits);
+    bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Compute, &options).unwrap();
+}
+
+#[test]
+fn resource_limits_conf() {
+    use bevy_glsl_to_spirv::ResourceLimits;
+
+    let defaults = ResourceLimits::default();
+    let conf = defaults.to_conf();
+    assert!(conf.starts_with("MaxLights 32\n"));
+    assert!(conf.contains("MaxMeshWorkGroupSizeX_NV 32\n"));
+    assert!(conf.contains("nonInductiveForLoops 1\n"));
+    assert_eq!(ResourceLimits::from_conf(&conf).unwrap(), defaults);
+
+    let limits: ResourceLimits = "MaxDrawBuffers 4\nMaxComputeWorkGroupSizeZ 128 whileLoops 0\n"
+        .parse()
+        .unwrap();
+    assert_eq!(limits.max_draw_buffers, 4);
+    assert_eq!(limits.max_compute_work_group_size_z, 128);
+    assert!(!limits.while_loops);
+    assert_eq!(limits.max_lights, defaults.max_lights);
+
+    // Unknown limits are skipped with a warning.
+    let conf = "MaxLights 8\nMaxUnicorns 1\nMaxDrawBuffers 2\n";
+    let (limits, warnings) = ResourceLimits::from_conf_with_warnings(conf).unwrap();
+    assert_eq!(limits, ResourceLimits::from_conf(conf).unwrap());
+    assert_eq!((limits.max_lights, limits.max_draw_buffers), (8, 2));
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].line, 2);
+    assert!(warnings[0].message.contains("MaxUnicorns"));
+    let err = ResourceLimits::from_conf("MaxLights\n").unwrap_err();
+    assert!(err.message.contains("missing value"));
+}
+
+#[test]
+fn resource_limit_presets() {
+    use bevy_glsl_to_spirv::{CompileOptions, ResourceLimits, ShaderType, TargetEnv};
+
+    let compute = r#"
+#version 450
+
+layout(local_size_x = 256) in;
+
+void main() {}
+"#;
+
+    bevy_glsl_to_spirv::compile(compute, ShaderType::Compute, None).unwrap();
+    bevy_glsl_to_spirv::compile_with_options(
+        compute,
+        ShaderType::Compute,
+        &CompileOptions::new().resource_limits(ResourceLimits::opengl_4_5()),
+    )
+    .unwrap();
+    for limits in &[
+        ResourceLimits::vulkan_1_0(),
+        ResourceLimits::opengl_es_3_1(),
+    ] {
+        let options = CompileOptions::new().resource_limits(limits.clone());
+        bevy_glsl_to_spirv::compile_with_options(compute, ShaderType::Compute, &options)
+            .unwrap_err();
+    }
+
+    let fragment = r#"
+#version 450
+
+layout(location = 5) out vec4 f_color;
+
+void main() {
+    f_color = vec4(1.0);
+}
+"#;
+
+    let options = CompileOptions::new().resource_limits(ResourceLimits::vulkan_1_0());
+    bevy_glsl_to_spirv::compile_with_options(fragment, ShaderType::Fragment, &options).unwrap_err();
+
+    // Built-in constants come from the preset. Each is read back as the size
+    // of an array in a uniform block.
+    let constant = |version: &str, options: &CompileOptions, name: &str| {
+        let shader = format!(
+            "#version {}\nprecision highp float;\n\
+             layout(std140, binding = 0) uniform Limits {{ vec4 values[{} + 1]; }};\n\
+             layout(location = 0) out vec4 f_color;\n\
+             void main() {{ f_color = values[0]; }}\n",
+            version, name
+        );
+        let output =
+            bevy_glsl_to_spirv::compile_with_options(&shader, ShaderType::Fragment, options)
+                .unwrap();
+        output.reflection.uniform_blocks[0].size / 16 - 1
+    };
+
+    let options = CompileOptions::new().resource_limits(ResourceLimits::opengl_es_3_1());
+    for &(name, value) in &[
+        ("gl_MaxVertexUniformVectors", 256),
+        ("gl_MaxFragmentUniformVectors", 224),
+        ("gl_MaxFragmentInputVectors", 15),
+        ("gl_MaxCombinedTextureImageUnits", 48),
+        ("gl_MaxDrawBuffers", 4),
+        ("gl_MaxFragmentImageUniforms", 0),
+        ("gl_MaxComputeUniformComponents", 512),
+    ] {
+        assert_eq!(constant("310 es", &options, name), value, "{}", name);
+    }
+
+    let options = CompileOptions::new().resource_limits(ResourceLimits::vulkan_1_0());
+    for &(name, value) in &[
+        ("gl_MaxVertexAttribs", 16),
+        ("gl_MaxFragmentInputComponents", 64),
+        ("gl_MaxTextureImageUnits", 16),
+        ("gl_MaxCombinedTextureImageUnits", 96),
+        ("gl_MaxFragmentImageUniforms", 4),
+        ("gl_MaxDrawBuffers", 4),
+        ("gl_MaxViewports", 1),
+    ] {
+        assert_eq!(constant("450", &options, name), value, "{}", name);
+    }
+
+    let options = CompileOptions::new()
+        .target_env(TargetEnv::OpenGl4_5)
+        .resource_limits(ResourceLimits::opengl_4_5());
+    for &(name, value) in &[
+        ("gl_MaxFragmentUniformComponents", 1024),
+        ("gl_MaxCombinedTextureImageUnits", 80),
+        ("gl_MaxDrawBuffers", 8),
+        ("gl_MaxComputeUniformComponents", 512),
+        ("gl_MaxViewports", 16),
+    ] {
+        assert_eq!(constant("450", &options, name), value, "{}", name);
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[test]
+fn validation() {
+    use bevy_glsl_to_spirv::{CompileOptions, ShaderType, TargetEnv};
+
+    let shader = r#"
+#version 450
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(1.0);
+}
+"#;
+
+    let options = CompileOptions::new().validate(true);
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Fragment, &options).unwrap();
+    bevy_glsl_to_spirv::validate(&output.spirv, TargetEnv::Vulkan1_0).unwrap();
+
+    let mut truncated = output.spirv.clone();
+    truncated.truncate(truncated.len() - 3);
+    let err = bevy_glsl_to_spirv::validate(&truncated, TargetEnv::Vulkan1_0).unwrap_err();
+    assert!(err.instruction.is_some());
+    assert!(!err.message.is_empty());
+
+    // A missing instruction isn't reported at any instruction.
+    let err = bevy_glsl_to_spirv::validate(&output.spirv[..5], TargetEnv::Vulkan1_0).unwrap_err();
+    assert_eq!(err.instruction, None);
+    assert!(err.message.contains("OpMemoryModel"), "{}", err.message);
+
+    // A SPIR-V 1.3 module is too new for Vulkan 1.0.
+    let mut newer = output.spirv;
+    newer[1] = 0x0001_0300;
+    bevy_glsl_to_spirv::validate(&newer, TargetEnv::Vulkan1_0).unwrap_err();
+    bevy_glsl_to_spirv::validate(&newer, TargetEnv::Vulkan1_1).unwrap();
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[test]
+fn disassembly() {
+    use bevy_glsl_to_spirv::{DisassembleOptions, ShaderType, SpirvVersion};
+
+    let shader = r#"
+#version 450
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(1.0);
+}
+"#;
+
+    let output = bevy_glsl_to_spirv::compile(shader, ShaderType::Fragment, None).unwrap();
+    let text = bevy_glsl_to_spirv::disassemble(&output.spirv).unwrap();
+    assert!(text.starts_with("; SPIR-V"));
+    assert!(text.contains("OpEntryPoint Fragment %main \"main\" %f_color"));
+    assert!(!text.contains('\x1b'));
+
+    let options = DisassembleOptions::new()
+        .friendly_names(false)
+        .header(false);
+    let plain = bevy_glsl_to_spirv::disassemble_with_options(&output.spirv, &options).unwrap();
+    assert!(!plain.contains("%main"));
+    assert!(!plain.starts_with(';'));
+
+    // Disassembly round-trips, apart from the generator in the header.
+    let assembled = bevy_glsl_to_spirv::assemble(&plain).unwrap();
+    assert_eq!(assembled[1], output.spirv[1]);
+    assert_eq!(assembled[3..], output.spirv[3..]);
+
+    let assembled =
+        bevy_glsl_to_spirv::assemble_with_version(&text, SpirvVersion::Spirv1_3).unwrap();
+    assert_eq!(assembled[1], 0x0001_0300);
+
+    let err =
+        bevy_glsl_to_spirv::assemble("OpCapability Shader\n%1 = OpNotAnInstruction\n").unwrap_err();
+    assert_eq!(err.line, Some(2));
+
+    bevy_glsl_to_spirv::disassemble(&output.spirv[..output.spirv.len() - 3]).unwrap_err();
+}
+
+#[test]
+fn reflection() {
+    use bevy_glsl_to_spirv::{ResourceKind, ShaderType};
+
+    let vertex = r#"
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec2 uv;
+
+layout(location = 0) out VertexData {
+    vec2 uv;
+    vec3 normal;
+} v_out;
+
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 view_proj;
+    vec3 eye;
+    float exposure;
+};
+
+layout(set = 1, binding = 2) readonly buffer Instances {
+    uint count;
+    mat4 transforms[];
+};
+
+void main() {
+    v_out.uv = uv;
+    v_out.normal = eye * exposure;
+    gl_Position = view_proj * transforms[count] * vec4(position, 1.0);
+}
+"#;
+
+    let fragment = r#"
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+layout(location = 1) out vec4 f_normal;
+
+layout(set = 2, binding = 0) uniform texture2D color_texture;
+layout(set = 2, binding = 1) uniform sampler color_sampler;
+layout(set = 2, binding = 2) uniform sampler2D lights[4];
+layout(set = 3, binding = 0, rgba8) uniform writeonly image2D target;
+
+void main() {
+    f_color = texture(sampler2D(color_texture, color_sampler), v_uv) + texture(lights[1], v_uv);
+    f_normal = vec4(0.0);
+    imageStore(target, ivec2(0), f_color);
+}
+"#;
+
+    let output = bevy_glsl_to_spirv::compile(vertex, ShaderType::Vertex, None).unwrap();
+    let reflection = output.reflection;
+
+    let camera = &reflection.uniform_blocks[0];
+    assert_eq!(
+        (camera.name.as_str(), camera.set, camera.binding),
+        ("Camera", 0, 0)
+    );
+    assert_eq!(camera.size, 80);
+    let members: Vec<_> = camera
+        .members
+        .iter()
+        .map(|m| (m.name.as_str(), m.offset, m.size, m.ty.as_str()))
+        .collect();
+    assert_eq!(
+        members,
+        [
+            ("view_proj", 0, 64, "mat4"),
+            ("eye", 64, 12, "vec3"),
+            ("exposure", 76, 4, "float"),
+        ]
+    );
+
+    let instances = &reflection.storage_buffers[0];
+    assert_eq!(
+        (instances.set, instances.binding, instances.size),
+        (1, 2, 16)
+    );
+    assert_eq!(instances.members[1].ty, "mat4[]");
+
+    let inputs: Vec<_> = reflection
+        .inputs
+        .iter()
+        .map(|v| (v.name.as_str(), v.location, v.ty.as_str()))
+        .collect();
+    assert_eq!(inputs, [("position", 0, "vec3"), ("uv", 1, "vec2")]);
+    let outputs: Vec<_> = reflection
+        .outputs
+        .iter()
+        .map(|v| (v.name.as_str(), v.location))
+        .collect();
+    assert_eq!(outputs, [("VertexData.uv", 0), ("VertexData.normal", 1)]);
+
+    let output = bevy_glsl_to_spirv::compile(fragment, ShaderType::Fragment, None).unwrap();
+    let reflection = output.reflection;
+    assert!(reflection.uniform_blocks.is_empty());
+    let resources: Vec<_> = reflection
+        .resources
+        .iter()
+        .map(|r| (r.name.as_str(), r.set, r.binding, r.count, r.kind))
+        .collect();
+    assert_eq!(
+        resources,
+        [
+            ("color_texture", 2, 0, 1, ResourceKind::SampledImage),
+            ("color_sampler", 2, 1, 1, ResourceKind::Sampler),
+            ("lights", 2, 2, 4, ResourceKind::CombinedImageSampler),
+            ("target", 3, 0, 1, ResourceKind::StorageImage),
+        ]
+    );
+    let outputs: Vec<_> = reflection.outputs.iter().map(|v| v.location).collect();
+    assert_eq!(outputs, [0, 1]);
+}
+
+#[test]
+fn reflect_module() {
+    use bevy_glsl_to_spirv::{DescriptorKind, ResourceKind, ShaderType};
+
+    let compute = r#"
+#version 450
+
+layout(constant_id = 0) const uint GROUP_SIZE = 64;
+layout(constant_id = 3) const bool CLAMP = true;
+layout(constant_id = 1) const float SCALE = 2.0;
+
+layout(local_size_x_id = 0, local_size_y = 2) in;
+
+layout(push_constant) uniform Params {
+    uint count;
+    float bias;
+} params;
+
+layout(set = 0, binding = 0) buffer Data {
+    float values[];
+};
+layout(set = 1, binding = 0) uniform sampler2D lut;
+
+void main() {
+    uint i = gl_GlobalInvocationID.x;
+    if (i < params.count) {
+        float v = values[i] * SCALE + params.bias + texture(lut, vec2(0.0)).r;
+        values[i] = CLAMP ? clamp(v, 0.0, 1.0) : v;
+    }
+}
+"#;
+
+    let output = bevy_glsl_to_spirv::compile(compute, ShaderType::Compute, None).unwrap();
+    let reflection = bevy_glsl_to_spirv::reflect(&output.spirv).unwrap();
+    assert_eq!(reflection, output.reflection);
+
+    let entry_point = &reflection.entry_points[0];
+    assert_eq!(entry_point.name, "main");
+    assert_eq!(entry_point.stage, Some(ShaderType::Compute));
+    // glslang gives the constant behind `local_size_x_id` a default of 1.
+    assert_eq!(entry_point.workgroup_size, Some([1, 2, 1]));
+
+    let push_constants = reflection.push_constants.as_ref().unwrap();
+    assert_eq!(push_constants.name, "Params");
+    assert_eq!((push_constants.offset, push_constants.size), (0, 8));
+
+    let constants: Vec<_> = reflection
+        .specialization_constants
+        .iter()
+        .map(|c| (c.id, c.name.as_str(), c.ty.as_str(), c.default))
+        .collect();
+    assert_eq!(
+        constants,
+        [
+            (0, "GROUP_SIZE", "uint", 64),
+            (1, "SCALE", "float", u64::from(2.0f32.to_bits())),
+            (3, "CLAMP", "bool", 1),
+        ]
+    );
+
+    let bindings: Vec<_> = reflection
+        .descriptor_bindings()
+        .iter()
+        .map(|b| (b.set, b.binding, b.count, b.kind))
+        .collect();
+    assert_eq!(
+        bindings,
+        [
+            (0, 0, 1, DescriptorKind::StorageBuffer),
+            (
+                1,
+                0,
+                1,
+                DescriptorKind::Resource(ResourceKind::CombinedImageSampler)
+            ),
+        ]
+    );
+    assert_eq!(reflection.descriptor_sets(), [0, 1]);
+
+    // Modules in the wrong byte order or with a truncated instruction are
+    // rejected.
+    let swapped: Vec<u32> = output.spirv.iter().map(|w| w.swap_bytes()).collect();
+    let err = bevy_glsl_to_spirv::reflect(&swapped).unwrap_err();
+    assert_eq!(err.instruction, None);
+    let mut truncated = output.spirv;
+    truncated.push(4 << 16 | 1);
+    let err = bevy_glsl_to_spirv::reflect(&truncated).unwrap_err();
+    assert!(err.instruction.is_some());
+}
+
+#[test]
+fn auto_map_bindings_and_locations() {
+    use bevy_glsl_to_spirv::{
+        Assignment, BindingClass, CompileOptions, ShaderType, SourceLanguage, TargetEnv,
+    };
+
+    let vertex = r#"
+#version 450
+
+layout(location = 3) in vec3 position;
+in vec2 uv;
+out vec2 v_uv;
+
+layout(set = 1) uniform Camera {
+    mat4 view_proj;
+};
+
+void main() {
+    v_uv = uv;
+    gl_Position = view_proj * vec4(position, 1.0);
+}
+"#;
+
+    let fragment = r#"
+#version 450
+
+in vec2 v_uv;
+out vec4 f_color;
+
+uniform sampler2D color_texture; // no binding
+layout(binding = 2) uniform sampler2D normal_texture;
+
+void main() {
+    f_color = texture(color_texture, v_uv) + texture(normal_texture, v_uv);
+}
+"#;
+
+    let shaders = [
+        (vertex, ShaderType::Vertex),
+        (fragment, ShaderType::Fragment),
+    ];
+    bevy_glsl_to_spirv::compile_program(&shaders, &CompileOptions::new()).unwrap_err();
+
+    let options = CompileOptions::new()
+        .auto_map_bindings(true)
+        .auto_map_locations(true);
+    let output = bevy_glsl_to_spirv::compile_program(&shaders, &options).unwrap();
+    let location = |stage, name: &str, location| Assignment::Location {
+        stage,
+        name: name.to_string(),
+        location,
+    };
+    let binding = |stage, name: &str, set, binding| Assignment::Binding {
+        stage,
+        name: name.to_string(),
+        set,
+        binding,
+    };
+    assert_eq!(
+        output.assignments,
+        [
+            location(ShaderType::Vertex, "uv", 0),
+            location(ShaderType::Vertex, "v_uv", 0),
+            binding(ShaderType::Vertex, "Camera", 1, 0),
+            location(ShaderType::Fragment, "v_uv", 0),
+            location(ShaderType::Fragment, "f_color", 0),
+            binding(ShaderType::Fragment, "color_texture", 0, 0),
+        ]
+    );
+
+    // Shifts apply to explicit bindings too, and the default set only to
+    // resources without one.
+    let options = options
+        .binding_shift(BindingClass::Texture, 10)
+        .binding_shift(BindingClass::UniformBuffer, 4)
+        .default_descriptor_set(2);
+    let output = bevy_glsl_to_spirv::compile_program(&shaders, &options).unwrap();
+    let bindings: Vec<_> = output
+        .assignments
+        .into_iter()
+        .filter(|a| matches!(a, Assignment::Binding { .. }))
+        .collect();
+    assert_eq!(
+        bindings,
+        [
+            binding(ShaderType::Vertex, "Camera", 1, 4),
+            binding(ShaderType::Fragment, "color_texture", 2, 10),
+        ]
+    );
+    let resources = &output.reflection[&ShaderType::Fragment].resources;
+    assert_eq!(
+        (resources[1].name.as_str(), resources[1].set),
+        ("normal_texture", 2)
+    );
+    assert_eq!(resources[1].binding, 12);
+
+    // OpenGL has no descriptor sets, so the default set is ignored.
+    let options = CompileOptions::new()
+        .target_env(TargetEnv::OpenGl4_5)
+        .auto_map_bindings(true)
+        .auto_map_locations(true)
+        .default_descriptor_set(2);
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(fragment, ShaderType::Fragment, &options).unwrap();
+    assert!(output.reflection.resources.iter().all(|r| r.set == 0));
+
+    // The set is applied by glslang, so declarations behind macros and HLSL
+    // resources get it too.
+    let hidden = r#"
+#version 450
+#define TEXTURE(name) layout(binding = 0) uniform sampler2D name
+TEXTURE(hidden_texture);
+layout(location = 0) out vec4 f_color;
+void main() {
+    f_color = texture(hidden_texture, vec2(0.0));
+}
+"#;
+    let options = CompileOptions::new().default_descriptor_set(3);
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(hidden, ShaderType::Fragment, &options).unwrap();
+    assert_eq!(output.reflection.resources[0].set, 3);
+
+    let hlsl = r#"
+Texture2D color_texture : register(t0);
+Texture2D normal_texture : register(t1, space1);
+SamplerState color_sampler : register(s0);
+float4 main(float2 uv : TEXCOORD0) : SV_Target {
+    return color_texture.Sample(color_sampler, uv) + normal_texture.Sample(color_sampler, uv);
+}
+"#;
+    let options = options.source_language(SourceLanguage::Hlsl);
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(hlsl, ShaderType::Fragment, &options).unwrap();
+    let sets: Vec<_> = output
+        .reflection
+        .resources
+        .iter()
+        .map(|r| (r.name.as_str(), r.set))
+        .collect();
+    assert_eq!(
+        sets,
+        [
+            ("normal_texture", 1),
+            ("color_texture", 3),
+            ("color_sampler", 3)
+        ]
+    );
+}
+
+#[test]
+fn repeated_failures() {
+    use bevy_glsl_to_spirv::{CompileOptions, ShaderType};
+
+    let broken = "#version 450\nvoid main() { undeclared = 1; }\n";
+    let vertex = "#version 450\nlayout(location = 0) out vec4 v;\nvoid main() { v = vec4(0.0); }\n";
+    let fragment = "#version 450\nlayout(location = 0) in vec3 v;\nvoid main() {}\n";
+
+    // Every failure has to release glslang's state for later compiles.
+    for _ in 0..10 {
+        bevy_glsl_to_spirv::compile(broken, ShaderType::Fragment, None).unwrap_err();
+        bevy_glsl_to_spirv::preprocess("#error failed\n", ShaderType::Fragment, None).unwrap_err();
+        let shaders = [
+            (vertex, ShaderType::Vertex),
+            (fragment, ShaderType::Fragment),
+        ];
+        bevy_glsl_to_spirv::compile_program(&shaders, &CompileOptions::new()).unwrap_err();
+    }
+    bevy_glsl_to_spirv::compile(vertex, ShaderType::Vertex, None).unwrap();
+}
+
+#[test]
+fn concurrent_compiles() {
+    use bevy_glsl_to_spirv::{CompileOptions, Compiler, ShaderType};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Compiler>();
+
+    let vertex = "#version 450\nlayout(location = 0) out vec4 v;\nvoid main() { v = vec4(0.0); }\n";
+    let broken = "#version 450\nvoid main() { undeclared = 1; }\n";
+    let expected = bevy_glsl_to_spirv::compile(vertex, ShaderType::Vertex, None)
+        .unwrap()
+        .spirv;
+
+    let compiler = Compiler::new();
+    std::thread::scope(|scope| {
+        for thread in 0..8 {
+            let compiler = compiler.clone();
+            let expected = &expected;
+            scope.spawn(move || {
+                for _ in 0..4 {
+                    let spirv = if thread % 2 == 0 {
+                        bevy_glsl_to_spirv::compile(vertex, ShaderType::Vertex, None)
+                    } else {
+                        compiler.compile(vertex, ShaderType::Vertex, &CompileOptions::new())
+                    };
+                    assert_eq!(&spirv.unwrap().spirv, expected);
+                    compiler
+                        .compile(broken, ShaderType::Fragment, &CompileOptions::new())
+                        .unwrap_err();
+                }
+            });
+        }
+    });
+
+    // Dropping the last handle of its own must not break later compiles.
+    drop(compiler);
+    let compiler = Compiler::new();
+    let output = compiler
+        .compile(vertex, ShaderType::Vertex, &CompileOptions::new())
+        .unwrap();
+    assert_eq!(output.spirv, expected);
+}
+
+#[test]
+fn compile_batch() {
+    use bevy_glsl_to_spirv::{CompileJob, CompileOptions, ShaderType};
+
+    let fragment = "#version 450\nlayout(location = 0) out vec4 color;\nvoid main() {\n#ifdef RED\n    color = vec4(1.0, 0.0, 0.0, 1.0);\n#else\n    color = vec4(1.0);\n#endif\n}\n";
+    let broken = "#version 450\nvoid main() { undeclared = 1; }\n";
+    let red = ["RED".to_string()];
+
+    let mut jobs = Vec::new();
+    for i in 0..16 {
+        jobs.push(match i % 3 {
+            0 => CompileJob::new(fragment, ShaderType::Fragment),
+            1 => CompileJob::new(fragment, ShaderType::Fragment).shader_defs(&red),
+            _ => CompileJob::new(broken, ShaderType::Fragment),
+        });
+    }
+    let results = bevy_glsl_to_spirv::compile_batch(&jobs);
+    assert_eq!(results.len(), jobs.len());
+
+    let white = bevy_glsl_to_spirv::compile(fragment, ShaderType::Fragment, None).unwrap();
+    let red = bevy_glsl_to_spirv::compile(fragment, ShaderType::Fragment, Some(&red)).unwrap();
+    assert_ne!(white.spirv, red.spirv);
+    for (i, result) in results.into_iter().enumerate() {
+        match i % 3 {
+            0 => assert_eq!(result.unwrap().spirv, white.spirv),
+            1 => assert_eq!(result.unwrap().spirv, red.spirv),
+            _ => assert!(result.is_err()),
+        }
+    }
+
+    assert!(bevy_glsl_to_spirv::compile_batch_with_options(&[], &CompileOptions::new()).is_empty());
+}
+
+#[test]
+fn shader_cache() {
+    use bevy_glsl_to_spirv::{
+        CacheStats, CompileOptions, FileIncludeResolver, ShaderCache, ShaderType,
+    };
+
+    let dir = std::env::temp_dir().join("bevy-glsl-to-spirv-cache");
+    let _ = std::fs::remove_dir_all(&dir);
+    let includes = dir.join("includes");
+    let entries = dir.join("entries");
+    std::fs::create_dir_all(&includes).unwrap();
+    std::fs::write(
+        includes.join("color.glsl"),
+        "const vec4 COLOR = vec4(1.0);\n",
+    )
+    .unwrap();
+
+    let shader = "#version 450\n#extension GL_GOOGLE_include_directive : require\n#include \"color.glsl\"\nlayout(location = 0) out vec4 color;\nvoid main() { color = COLOR; }\n";
+    let options =
+        CompileOptions::new().include_resolver(FileIncludeResolver::new().include_dir(&includes));
+    let stats = |memory_hits, disk_hits, misses| CacheStats {
+        memory_hits,
+        disk_hits,
+        misses,
+    };
+
+    let cache = ShaderCache::new(4).directory(&entries, u64::MAX);
+    let compiled = cache
+        .compile_with_options(shader, ShaderType::Fragment, &options)
+        .unwrap();
+    let cached = cache
+        .compile_with_options(shader, ShaderType::Fragment, &options)
+        .unwrap();
+    assert_eq!(cached, compiled);
+    assert_eq!(cache.stats(), stats(1, 0, 1));
+
+    // Defines, options and included files are all part of the key.
+    let defs = ["UNUSED".to_string()];
+    let defined = options.clone().shader_defs(&defs);
+    cache
+        .compile_with_options(shader, ShaderType::Fragment, &defined)
+        .unwrap();
+    let mapped = options.clone().auto_map_locations(true);
+    cache
+        .compile_with_options(shader, ShaderType::Fragment, &mapped)
+        .unwrap();
+    assert_eq!(cache.stats(), stats(1, 0, 3));
+    std::fs::write(
+        includes.join("color.glsl"),
+        "const vec4 COLOR = vec4(0.5);\n",
+    )
+    .unwrap();
+    let changed = cache
+        .compile_with_options(shader, ShaderType::Fragment, &options)
+        .unwrap();
+    assert_ne!(changed.spirv, compiled.spirv);
+    assert_eq!(cache.stats(), stats(1, 0, 4));
+
+    // A new cache, as after a restart, finds the outputs on disk.
+    std::fs::write(
+        includes.join("color.glsl"),
+        "const vec4 COLOR = vec4(1.0);\n",
+    )
+    .unwrap();
+    let cache = ShaderCache::new(4).directory(&entries, u64::MAX);
+    let loaded = cache
+        .compile_with_options(shader, ShaderType::Fragment, &options)
+        .unwrap();
+    assert_eq!(loaded, compiled);
+    assert_eq!(cache.stats(), stats(0, 1, 0));
+
+    // An entry stored for another input under the same file name is a miss.
+    let other =
+        "#version 450\nlayout(location = 0) out vec4 color;\nvoid main() { color = vec4(0.0); }\n";
+    let other_entries = dir.join("other");
+    let cache = ShaderCache::new(0).directory(&other_entries, u64::MAX);
+    cache.compile(other, ShaderType::Fragment, None).unwrap();
+    let collided = dir.join("collided");
+    let cache = ShaderCache::new(0).directory(&collided, u64::MAX);
+    cache
+        .compile_with_options(shader, ShaderType::Fragment, &options)
+        .unwrap();
+    let only_file = |dir: &std::path::Path| {
+        let mut files = std::fs::read_dir(dir).unwrap();
+        let file = files.next().unwrap().unwrap().path();
+        assert!(files.next().is_none());
+        file
+    };
+    std::fs::copy(only_file(&other_entries), only_file(&collided)).unwrap();
+    let cache = ShaderCache::new(0).directory(&collided, u64::MAX);
+    let recompiled = cache
+        .compile_with_options(shader, ShaderType::Fragment, &options)
+        .unwrap();
+    assert_eq!(recompiled, compiled);
+    assert_eq!(cache.stats(), stats(0, 0, 1));
+
+    // Corrupted files are deleted and recompiled.
+    let files: Vec<_> = std::fs::read_dir(&entries)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 4);
+    for file in &files {
+        std::fs::write(file, b"garbage").unwrap();
+    }
+    let cache = ShaderCache::new(0).directory(&entries, 0);
+    let recompiled = cache
+        .compile_with_options(shader, ShaderType::Fragment, &options)
+        .unwrap();
+    assert_eq!(recompiled, compiled);
+    assert_eq!(cache.stats(), stats(0, 0, 1));
+
+    // Nothing fits in a directory of size 0.
+    assert_eq!(std::fs::read_dir(&entries).unwrap().count(), 0);
+
+    // Failures aren't cached.
+    let broken = "#version 450\nvoid main() { undeclared = 1; }\n";
+    cache
+        .compile(broken, ShaderType::Fragment, None)
+        .unwrap_err();
+    cache
+        .compile(broken, ShaderType::Fragment, None)
+        .unwrap_err();
+    assert_eq!(cache.stats(), stats(0, 0, 3));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn command_line() {
+    use std::process::Command;
+
+    let dir = std::env::temp_dir().join("bevy-glsl-to-spirv-cli");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let shader = dir.join("color.frag.glsl");
+    std::fs::write(
+        &shader,
+        "#version 450\nlayout(location = 0) out vec4 color;\nvoid main() { color = COLOR; }\n",
+    )
+    .unwrap();
+    let cli = || Command::new(env!("CARGO_BIN_EXE_glsl-to-spirv"));
+
+    let output = cli()
+        .arg("-DCOLOR=vec4(1.0)")
+        .arg("--reflect")
+        .arg(&shader)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("location 0: vec4 color"));
+    let spirv = std::fs::read(dir.join("color.frag.glsl.spv")).unwrap();
+    assert_eq!(spirv[..4], 0x0723_0203u32.to_le_bytes());
+
+    let output = cli().arg("-S").arg("frag").arg(&shader).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with(&format!("{}:3: error: 'COLOR'", shader.display())));
+
+    // Errors in included files point into them.
+    let include_dir = dir.join("include");
+    std::fs::create_dir_all(&include_dir).unwrap();
+    let header = include_dir.join("broken.glsl");
+    std::fs::write(&header, "const vec4 BROKEN = vec4(1.0;\n").unwrap();
+    let includer = dir.join("includer.frag");
+    std::fs::write(
+        &includer,
+        "#version 450\n#include <broken.glsl>\nlayout(location = 0) out vec4 color;\nvoid main() { color = BROKEN; }\n",
+    )
+    .unwrap();
+    let output = cli()
+        .arg("-I")
+        .arg(&include_dir)
+        .arg(&includer)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with(&format!("{}:1: error:", header.display())));
+
+    let output = cli().arg("-S").arg("pixel").arg(&shader).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn conditional_includes() {
+    use bevy_glsl_to_spirv::{
+        CompileOptions, IncludeResolver, IncludeType, ResolvedInclude, ShaderType,
+    };
+    use std::collections::HashMap;
+
+    struct MapResolver(HashMap<&'static str, &'static str>);
+
+    impl IncludeResolver for MapResolver {
+        fn resolve(
+            &self,
+            name: &str,
+            _ty: IncludeType,
+            _requesting: &str,
+            _depth: usize,
+        ) -> Result<ResolvedInclude, String> {
+            let content = self.0.get(name).ok_or("not found")?;
+            Ok(ResolvedInclude {
+                name: name.to_string(),
+                content: content.to_string(),
+            })
+        }
+    }
+
+    let mut files = HashMap::new();
+    files.insert(
+        "a.glsl",
+        "#ifndef A_GLSL\n#define A_GLSL\n#include \"b.glsl\"\nfloat a() { return b(); }\n#endif\n",
+    );
+    files.insert(
+        "b.glsl",
+        "#ifndef B_GLSL\n#define B_GLSL\n#include \"a.glsl\"\nfloat b() { return 2.0; }\n#endif\n",
+    );
+    files.insert("once.glsl", "#pragma once\nconst float ONCE = 1.0;\n");
+    files.insert("c.glsl", "#include \"d.glsl\"\nfloat c() { return d(); }\n");
+    files.insert("d.glsl", "float d() { return 1.0; }\n#include \"c.glsl\"\n");
+    let options = CompileOptions::new()
+        .include_resolver(MapResolver(files))
+        .define("FEATURE", Some("1"));
+
+    // Mutually including headers behind guards, a guarded header included
+    // again and a `#pragma once` header included twice.
+    let shader = r#"
+#version 450
+#include "a.glsl"
+#include "b.glsl"
+#include "once.glsl"
+#include "once.glsl"
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(a() + b() + ONCE);
+}
+"#;
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Fragment, &options).unwrap();
+    assert_eq!(output.includes, ["a.glsl", "b.glsl", "once.glsl"]);
+
+    // Includes in inactive branches don't have to exist.
+    let shader = r#"
+#version 450
+#if 0
+#include "missing.glsl"
+#endif
+#ifdef UNDEFINED_FEATURE
+#include "missing.glsl"
+#elif defined(FEATURE) && FEATURE > 1
+#include "missing.glsl"
+#else
+#include "once.glsl"
+#endif
+#ifdef GL_EXT_unknown_extension
+#include "missing.glsl"
+#endif
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(ONCE);
+}
+"#;
+    let output =
+        bevy_glsl_to_spirv::compile_with_options(shader, ShaderType::Fragment, &options).unwrap();
+    assert_eq!(output.includes, ["once.glsl"]);
+
+    let active = shader.replace("#if 0", "#if FEATURE");
+    let err = bevy_glsl_to_spirv::compile_with_options(&active, ShaderType::Fragment, &options)
+        .unwrap_err();
+    assert_eq!(err.diagnostics()[0].token.as_deref(), Some("missing.glsl"));
+
+    // Cycles without guards point at the directive closing them.
+    let cycle = "#version 450\n#include \"c.glsl\"\nvoid main() {}\n";
+    let err = bevy_glsl_to_spirv::compile_with_options(cycle, ShaderType::Fragment, &options)
+        .unwrap_err();
+    let diagnostic = &err.diagnostics()[0];
+    assert_eq!(err.includes(), ["c.glsl", "d.glsl"]);
+    assert_eq!((diagnostic.source, diagnostic.line), (Some(2), Some(2)));
+    assert_eq!(diagnostic.token.as_deref(), Some("c.glsl"));
+    assert_eq!(
+        diagnostic.message,
+        "recursive include: c.glsl -> d.glsl -> c.glsl"
+    );
+}